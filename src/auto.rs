@@ -0,0 +1,245 @@
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+use crate::expander::bitman::BitManipulatorExpander;
+use crate::expander::constraint::ItemMask;
+use crate::expander::hasher::{with_hash_set, HasherKind};
+use crate::expander::levelwise::LevelWiseExpander;
+use crate::expander::set::Wrappedu128;
+use crate::expander::table::IntTable;
+use crate::expander::vec::VecExpander;
+use crate::expander::{ExpandOptions, Expander};
+use crate::JsonSet;
+
+/// Predicted number of item-sets above which the level-wise expander is picked, so that
+/// only the largest size level is held in memory.
+const LEVEL_WISE_PREDICTED_LEN: f64 = (1u64 << 28) as f64;
+
+/// Shape of the input item-sets the expander is picked from. The `--require` items count
+/// as used items since they are remapped and stored too, even if no input holds them.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct InputProfile {
+    pub(crate) max_item: Option<u8>,
+    pub(crate) distinct_items: usize,
+    pub(crate) max_len: usize,
+    pub(crate) mean_len: f64,
+    /// Upper bound on the number of expanded item-sets within the size bounds: the subsets
+    /// of every input item-set counted apart, at most all the item-sets of the distinct items.
+    pub(crate) predicted_len: f64,
+}
+
+/// Number of item-sets of `len` items with a size within the bounds of `options`.
+fn nb_subsets(len: usize, options: &ExpandOptions) -> f64 {
+    let mut binomial = 1.0;
+    let mut nb = 0.0;
+    for size in 0..=len.min(options.max_size) {
+        if size >= options.min_size {
+            nb += binomial;
+        }
+        binomial = binomial * (len - size) as f64 / (size + 1) as f64;
+    }
+    nb
+}
+
+impl InputProfile {
+    pub(crate) fn new(parsed_set: &[JsonSet], options: &ExpandOptions) -> Self {
+        let items = parsed_set
+            .iter()
+            .flat_map(|x| x.set.iter().copied())
+            .chain(options.constraints.required.items());
+        let distinct_items = items.clone().collect::<ItemMask>().len();
+        let total_len = parsed_set.iter().map(|x| x.set.len()).sum::<usize>();
+        let predicted_len = parsed_set
+            .iter()
+            .map(|x| nb_subsets(x.set.len(), options))
+            .sum::<f64>()
+            .min(nb_subsets(distinct_items, options));
+        InputProfile {
+            max_item: items.max(),
+            distinct_items,
+            max_len: parsed_set.iter().map(|x| x.set.len()).max().unwrap_or(0),
+            mean_len: total_len as f64 / parsed_set.len().max(1) as f64,
+            predicted_len,
+        }
+    }
+
+    /// Fastest expander fitting the input: the Bit Manipulator Expander if the items fit in
+    /// 128 bits, remapped if only their number does, the Vec Expander otherwise, and the
+    /// level-wise expander when the output is predicted to be too large to hold. The Hash-only
+    /// Vec Expander is never picked as it may lose item-sets to collisions.
+    pub(crate) fn pick_expander(&self) -> AutoExpander {
+        if self.predicted_len > LEVEL_WISE_PREDICTED_LEN {
+            AutoExpander::LevelWise
+        } else if self.max_item.is_none_or(|x| x < 128) {
+            AutoExpander::BitMan { remap: false }
+        } else if self.distinct_items <= 128 {
+            AutoExpander::BitMan { remap: true }
+        } else {
+            AutoExpander::Vec
+        }
+    }
+
+    pub(crate) fn print(&self, expander: AutoExpander) {
+        println!(
+            "Auto: {} distinct items (max id {}), item-sets of {:.1} items on average (at most {}), at most {:.3e} expanded item-sets -> {}",
+            self.distinct_items,
+            self.max_item.unwrap_or_default(),
+            self.mean_len,
+            self.max_len,
+            self.predicted_len,
+            expander.name()
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AutoExpander {
+    BitMan { remap: bool },
+    Vec,
+    LevelWise,
+}
+
+impl AutoExpander {
+    fn name(self) -> &'static str {
+        match self {
+            AutoExpander::BitMan { remap: false } => "Bit Manipulator Expander",
+            AutoExpander::BitMan { remap: true } => "Bit Manipulator Expander on remapped items",
+            AutoExpander::Vec => "Vec Expander",
+            AutoExpander::LevelWise => "Level-wise Vec Expander",
+        }
+    }
+
+    /// Hashers the expander can use, the Int Table only fits the Bit Manipulator Expander.
+    fn hashers(self) -> impl Iterator<Item = HasherKind> {
+        HasherKind::ALL
+            .into_iter()
+            .filter(move |&h| h != HasherKind::Table || matches!(self, AutoExpander::BitMan { .. }))
+    }
+
+    fn time(
+        self,
+        hasher: HasherKind,
+        parsed_set: &[JsonSet],
+        options: &ExpandOptions,
+    ) -> Result<Duration> {
+        let parsed_set = parsed_set.to_vec();
+        let start = Instant::now();
+        match (self, hasher) {
+            (AutoExpander::BitMan { .. }, HasherKind::Table) => {
+                BitManipulatorExpander::<IntTable<Wrappedu128>>::expand_into(
+                    parsed_set,
+                    IntTable::with_expected_len(0),
+                    options,
+                );
+            }
+            (AutoExpander::BitMan { .. }, _) => with_hash_set!(hasher, Set, _H => {
+                BitManipulatorExpander::<Set<Wrappedu128>>::expand_with(parsed_set, options);
+            }),
+            (AutoExpander::Vec, _) => with_hash_set!(hasher, Set, _H => {
+                VecExpander::<Set<Vec<u8>>>::expand_with(parsed_set, options);
+            }),
+            (AutoExpander::LevelWise, _) => with_hash_set!(hasher, Set, _H => {
+                LevelWiseExpander::<Set<Vec<u8>>>::expand_streaming(parsed_set, options, &mut |_| Ok(()))?;
+            }),
+        }
+        Ok(start.elapsed())
+    }
+
+    /// Expands the sampled input item-sets with every Hasher and returns the fastest one.
+    pub(crate) fn pick_hasher(
+        self,
+        sampled_set: &[JsonSet],
+        options: &ExpandOptions,
+    ) -> Result<HasherKind> {
+        // warms up the allocator and caches, which would otherwise slow down the first Hasher.
+        self.time(HasherKind::Fnv, sampled_set, options)?;
+        let mut fastest = (HasherKind::Fnv, Duration::MAX);
+        for hasher in self.hashers() {
+            let elapsed = self.time(hasher, sampled_set, options)?;
+            println!(
+                "Auto: {:<12}{:>10.3} ms",
+                hasher.name(),
+                elapsed.as_secs_f64() * 1e3
+            );
+            if elapsed < fastest.1 {
+                fastest = (hasher, elapsed);
+            }
+        }
+        Ok(fastest.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_input_profile() {
+        let parsed_set = vec![
+            JsonSet { set: vec![1, 2, 3] },
+            JsonSet { set: vec![3, 200] },
+        ];
+        let profile = InputProfile::new(&parsed_set, &ExpandOptions::default());
+        assert_eq!(profile.max_item, Some(200));
+        assert_eq!(profile.distinct_items, 4);
+        assert_eq!(profile.max_len, 3);
+        assert_eq!(profile.predicted_len, 10.0);
+        assert_eq!(
+            profile.pick_expander(),
+            AutoExpander::BitMan { remap: true }
+        );
+        let options = ExpandOptions {
+            min_size: 2,
+            max_size: 2,
+            ..ExpandOptions::default()
+        };
+        assert_eq!(InputProfile::new(&parsed_set, &options).predicted_len, 4.0);
+    }
+
+    #[test]
+    fn test_required_items() {
+        let parsed_set = vec![JsonSet {
+            set: (100..=227).collect(),
+        }];
+        let mut options = ExpandOptions {
+            max_size: 2,
+            ..ExpandOptions::default()
+        };
+        let profile = InputProfile::new(&parsed_set, &options);
+        assert_eq!(
+            profile.pick_expander(),
+            AutoExpander::BitMan { remap: true }
+        );
+        options.constraints.required = [5].into_iter().collect();
+        let profile = InputProfile::new(&parsed_set, &options);
+        assert_eq!(profile.distinct_items, 129);
+        assert_eq!(profile.pick_expander(), AutoExpander::Vec);
+        let parsed_set = vec![JsonSet { set: vec![1, 2] }];
+        options.constraints.required = [200].into_iter().collect();
+        let profile = InputProfile::new(&parsed_set, &options);
+        assert_eq!(profile.max_item, Some(200));
+        assert_eq!(
+            profile.pick_expander(),
+            AutoExpander::BitMan { remap: true }
+        );
+    }
+
+    #[test]
+    fn test_pick_expander() {
+        let profile = InputProfile {
+            max_item: Some(255),
+            distinct_items: 200,
+            max_len: 40,
+            mean_len: 20.0,
+            predicted_len: 1e6,
+        };
+        assert_eq!(profile.pick_expander(), AutoExpander::Vec);
+        let profile = InputProfile {
+            max_item: Some(60),
+            predicted_len: 1e12,
+            ..profile
+        };
+        assert_eq!(profile.pick_expander(), AutoExpander::LevelWise);
+    }
+}
@@ -0,0 +1,268 @@
+use anyhow::Result;
+use fnv::FnvHasher;
+use serde::Serialize;
+use std::io::Write;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::expander::bitman::BitManipulatorExpander;
+use crate::expander::bitvec::BitVecExpander;
+use crate::expander::hasher::{with_hash_set, HasherKind};
+use crate::expander::set::{SerializedSetLen, WrappedBitVec, Wrappedu128};
+use crate::expander::table::IntTable;
+use crate::expander::vec::VecExpander;
+use crate::expander::vechashonly::VecHashOnlyExpander;
+use crate::expander::{ExpandOptions, Expander};
+use crate::JsonSet;
+
+/// Expanders dispatched in `work`, each run with every selected Hasher it supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum BenchExpander {
+    Vec,
+    HashOnly,
+    BitVec,
+    BitMan,
+}
+
+impl BenchExpander {
+    pub(crate) const ALL: [BenchExpander; 4] = [
+        BenchExpander::Vec,
+        BenchExpander::HashOnly,
+        BenchExpander::BitVec,
+        BenchExpander::BitMan,
+    ];
+
+    pub(crate) const NAMES: [&'static str; 4] = ["vec", "hash-only", "bit-vec", "bit-man"];
+
+    pub(crate) fn name(self) -> &'static str {
+        Self::NAMES[Self::ALL.iter().position(|&x| x == self).unwrap()]
+    }
+
+    /// The Int Table only supports the Bit Manipulator and Hash-only Vec Expanders.
+    pub(crate) fn supports(self, hasher: HasherKind) -> bool {
+        hasher != HasherKind::Table
+            || matches!(self, BenchExpander::HashOnly | BenchExpander::BitMan)
+    }
+
+    /// Expands the input item-sets and returns the number of item-sets, of hashes for the
+    /// Hash-only Vec Expander.
+    pub(crate) fn run(
+        self,
+        hasher: HasherKind,
+        parsed_set: Vec<JsonSet>,
+        options: &ExpandOptions,
+    ) -> Result<usize> {
+        let len = match (self, hasher) {
+            (BenchExpander::HashOnly, HasherKind::Table) => {
+                VecHashOnlyExpander::<IntTable<u64>, FnvHasher, u64>::expand_streaming(
+                    parsed_set,
+                    IntTable::with_expected_len(0),
+                    options,
+                    &mut |_| {},
+                )
+                .set_len()
+            }
+            (BenchExpander::BitMan, HasherKind::Table) => {
+                BitManipulatorExpander::<IntTable<Wrappedu128>>::expand_into(
+                    parsed_set,
+                    IntTable::with_expected_len(0),
+                    options,
+                )
+                .set_len()
+            }
+            (BenchExpander::Vec, _) => with_hash_set!(hasher, Set, _H => {
+                VecExpander::<Set<Vec<u8>>>::expand_with(parsed_set, options).set_len()
+            }),
+            (BenchExpander::HashOnly, _) => with_hash_set!(hasher, Set, H => {
+                VecHashOnlyExpander::<Set<u64>, H, u64>::expand_streaming(
+                    parsed_set,
+                    Set::default(),
+                    options,
+                    &mut |_| {},
+                )
+                .set_len()
+            }),
+            (BenchExpander::BitVec, _) => with_hash_set!(hasher, Set, _H => {
+                BitVecExpander::<Set<WrappedBitVec>>::expand_with(parsed_set, options).set_len()
+            }),
+            (BenchExpander::BitMan, _) => with_hash_set!(hasher, Set, _H => {
+                BitManipulatorExpander::<Set<Wrappedu128>>::expand_with(parsed_set, options).set_len()
+            }),
+        };
+        Ok(len)
+    }
+}
+
+impl FromStr for BenchExpander {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::NAMES
+            .iter()
+            .position(|&name| name == s)
+            .map(|i| Self::ALL[i])
+            .ok_or_else(|| format!("unknown expander '{}', use one of {:?}", s, Self::NAMES))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum BenchFormat {
+    Table,
+    Json,
+}
+
+impl BenchFormat {
+    pub(crate) const NAMES: [&'static str; 2] = ["table", "json"];
+}
+
+impl FromStr for BenchFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(BenchFormat::Table),
+            "json" => Ok(BenchFormat::Json),
+            _ => Err(format!(
+                "unknown format '{}', use one of {:?}",
+                s,
+                Self::NAMES
+            )),
+        }
+    }
+}
+
+/// Measurements of the runs of one expander and Hasher.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct BenchResult {
+    pub(crate) expander: BenchExpander,
+    pub(crate) hasher: String,
+    pub(crate) runs: usize,
+    /// Mean and fastest wall time of the runs in seconds.
+    pub(crate) mean_secs: f64,
+    pub(crate) min_secs: f64,
+    /// Item-sets per second over the mean wall time.
+    pub(crate) throughput: f64,
+    /// Peak resident memory of the process during the runs in kB, `None` where the peak
+    /// cannot be read and reset (outside Linux).
+    pub(crate) peak_memory_kb: Option<u64>,
+    /// Number of item-sets, of hashes for the Hash-only Vec Expander.
+    pub(crate) len: usize,
+}
+
+impl BenchResult {
+    pub(crate) fn new(
+        expander: BenchExpander,
+        hasher: HasherKind,
+        times: &[Duration],
+        peak_memory_kb: Option<u64>,
+        len: usize,
+    ) -> Self {
+        let secs = times.iter().map(Duration::as_secs_f64);
+        let mean_secs = secs.clone().sum::<f64>() / times.len().max(1) as f64;
+        BenchResult {
+            expander,
+            hasher: hasher.name().to_string(),
+            runs: times.len(),
+            mean_secs,
+            min_secs: secs.reduce(f64::min).unwrap_or_default(),
+            throughput: len as f64 / mean_secs,
+            peak_memory_kb,
+            len,
+        }
+    }
+}
+
+/// Resets the peak resident memory of the process to the current one, returns whether it
+/// is supported.
+pub(crate) fn reset_peak_memory() -> bool {
+    std::fs::write("/proc/self/clear_refs", "5").is_ok()
+}
+
+/// Peak resident memory of the process in kB since the last reset.
+pub(crate) fn peak_memory_kb() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()
+}
+
+pub(crate) fn write_results(
+    results: &[BenchResult],
+    format: BenchFormat,
+    mut writer: impl Write,
+) -> Result<()> {
+    match format {
+        BenchFormat::Json => serde_json::to_writer(&mut writer, results)?,
+        BenchFormat::Table => {
+            writeln!(
+                writer,
+                "{:<12}{:<12}{:>6}{:>12}{:>12}{:>16}{:>14}{:>12}",
+                "expander",
+                "hasher",
+                "runs",
+                "mean (s)",
+                "min (s)",
+                "item-sets/s",
+                "peak (MiB)",
+                "item-sets"
+            )?;
+            for r in results {
+                let peak_memory = r
+                    .peak_memory_kb
+                    .map_or("-".to_string(), |kb| format!("{:.1}", kb as f64 / 1024.0));
+                writeln!(
+                    writer,
+                    "{:<12}{:<12}{:>6}{:>12.4}{:>12.4}{:>16.0}{:>14}{:>12}",
+                    r.expander.name(),
+                    r.hasher,
+                    r.runs,
+                    r.mean_secs,
+                    r.min_secs,
+                    r.throughput,
+                    peak_memory,
+                    r.len
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_bench_result() {
+        let times = [Duration::from_millis(100), Duration::from_millis(300)];
+        let result = BenchResult::new(
+            BenchExpander::BitMan,
+            HasherKind::Fx,
+            &times,
+            Some(2048),
+            1000,
+        );
+        assert_eq!(result.runs, 2);
+        assert!((result.mean_secs - 0.2).abs() < 1e-9);
+        assert!((result.min_secs - 0.1).abs() < 1e-9);
+        assert!((result.throughput - 5000.0).abs() < 1e-6);
+        let mut json = Vec::new();
+        write_results(&[result], BenchFormat::Json, &mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains(r#""expander":"bit-man","hasher":"fx""#));
+    }
+
+    #[test]
+    fn test_supports() {
+        assert!(BenchExpander::BitMan.supports(HasherKind::Table));
+        assert!(!BenchExpander::Vec.supports(HasherKind::Table));
+        assert_eq!("hash-only".parse(), Ok(BenchExpander::HashOnly));
+    }
+}
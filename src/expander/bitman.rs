@@ -1,6 +1,10 @@
-use crate::{expander::Expander, JsonSet};
+use crate::{
+    expander::{descend_to_max_size, ExpandOptions, Expander},
+    JsonSet,
+};
 
 use super::set::Wrappedu128;
+use std::convert::Infallible;
 
 fn convert_itemset(sol: &[u8]) -> Wrappedu128 {
     let mut sol_u128 = 0;
@@ -23,31 +27,50 @@ where
     type SetType = T;
     type HashType = Wrappedu128;
 
-    fn expand(parsed_set: Vec<JsonSet>) -> T {
-        let mut final_set = T::default();
-        let parsed_set = parsed_set
+    fn expand_into(parsed_set: Vec<JsonSet>, mut final_set: T, options: &ExpandOptions) -> T {
+        let parsed_set = options
+            .constraints
+            .constrain_input(parsed_set)
             .iter()
             .map(|x| convert_itemset(&x.set))
             .collect::<Vec<_>>();
         for mut i in parsed_set {
-            Self::expand_one_solution_to_lower_level(&mut i, &mut final_set);
+            Self::expand_one_solution_to_lower_level(&mut i, &mut final_set, options);
         }
         final_set
     }
 
-    fn expand_one_solution_to_lower_level(solution: &mut Self::SolutionType, final_set: &mut T) {
-        let length = solution.0.count_ones();
-        if length > 1 {
+    fn expand_one_solution_to_lower_level(
+        solution: &mut Self::SolutionType,
+        final_set: &mut T,
+        options: &ExpandOptions,
+    ) {
+        if options.is_below_min_utility(solution.items()) {
+            return;
+        }
+        let length = solution.0.count_ones() as usize;
+        if length > options.max_size {
+            let Ok(()) = descend_to_max_size(solution, length, 0, options, &mut |x| {
+                if !final_set.set_contains(x) {
+                    Self::expand_one_solution_to_lower_level(x, final_set, options);
+                }
+                Ok::<(), Infallible>(())
+            });
+            return;
+        }
+        if length > options.min_size {
             for i in 0..u128::BITS {
-                if (solution.0 & (1 << i)) >> i == 1 {
+                if (solution.0 & (1 << i)) >> i == 1 && !options.constraints.is_required(i as u8) {
                     let mut new_sol = Wrappedu128(solution.0 ^ (1 << i));
                     if !final_set.set_contains(&new_sol) {
-                        Self::expand_one_solution_to_lower_level(&mut new_sol, final_set);
+                        Self::expand_one_solution_to_lower_level(&mut new_sol, final_set, options);
                     }
                 }
             }
         }
-        final_set.set_insert(*solution);
+        if length >= options.min_size {
+            final_set.set_insert(*solution);
+        }
     }
 }
 
@@ -60,6 +83,9 @@ mod tests {
     use fxhash::FxHashSet;
 
     use crate::expander::set::WrappedAHashSet;
+    use crate::expander::table::IntTable;
+
+    use crate::expander::utility::{ItemWeights, MinUtility};
 
     use super::*;
     #[test]
@@ -186,4 +212,64 @@ mod tests {
         let serialized_set = serde_json::to_string(&expanded_set).unwrap();
         assert_eq!(serialized_set.len(), 140);
     }
+
+    #[test]
+    fn test_1_table() {
+        let parsed_set = vec![
+            JsonSet { set: vec![1, 2, 3] },
+            JsonSet { set: vec![4, 5, 6] },
+        ];
+        assert_eq!(
+            BitManipulatorExpander::<IntTable<Wrappedu128>>::expand(parsed_set).len(),
+            14
+        );
+    }
+    #[test]
+    fn test_2_table() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![57, 58, 59, 60],
+            },
+            JsonSet { set: vec![60, 99] },
+        ];
+        assert_eq!(
+            BitManipulatorExpander::<IntTable<Wrappedu128>>::expand(parsed_set).len(),
+            17
+        );
+    }
+
+    #[test]
+    fn test_2_empty_set_table() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![57, 58, 59, 60],
+            },
+            JsonSet { set: vec![60, 99] },
+        ];
+        let options = ExpandOptions {
+            min_size: 0,
+            ..ExpandOptions::default()
+        };
+        let expanded_set =
+            BitManipulatorExpander::<IntTable<Wrappedu128>>::expand_with(parsed_set, &options);
+        assert_eq!(expanded_set.len(), 18);
+    }
+
+    #[test]
+    fn test_1_min_utility() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![1, 2, 3, 4],
+            },
+            JsonSet { set: vec![4, 5, 6] },
+        ];
+        let weights = ItemWeights::from_json(r#"{"1": 1, "2": 2, "3": 3, "4": 4, "5": 5, "6": 6}"#);
+        let options = ExpandOptions {
+            min_utility: Some(MinUtility::new(weights.unwrap(), 7.0).unwrap()),
+            ..ExpandOptions::default()
+        };
+        let expanded_set =
+            BitManipulatorExpander::<FnvHashSet<Wrappedu128>>::expand_with(parsed_set, &options);
+        assert_eq!(expanded_set.len(), 9);
+    }
 }
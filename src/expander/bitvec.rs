@@ -1,6 +1,10 @@
 use crate::expander::set::WrappedBitVec;
-use crate::{expander::Expander, JsonSet};
+use crate::{
+    expander::{descend_to_max_size, ExpandOptions, Expander},
+    JsonSet,
+};
 use bitvec::vec::BitVec;
+use std::convert::Infallible;
 
 fn convert_itemset(itemset: &[u8], nb_bits: usize) -> WrappedBitVec {
     let mut bv = BitVec::with_capacity(nb_bits);
@@ -12,12 +16,12 @@ fn convert_itemset(itemset: &[u8], nb_bits: usize) -> WrappedBitVec {
 }
 
 fn get_number_of_required_bits(parsed_set: &[JsonSet]) -> usize {
-    *parsed_set
+    // constrained inputs may be empty after removing the forbidden items.
+    parsed_set
         .iter()
-        .map(|x| x.set.iter().max().unwrap())
+        .flat_map(|x| x.set.iter())
         .max()
-        .unwrap() as usize
-        + 1
+        .map_or(0, |&x| x as usize + 1)
 }
 
 pub(crate) struct BitVecExpander<T> {
@@ -33,33 +37,51 @@ where
     type SetType = T;
     type HashType = WrappedBitVec;
 
-    fn expand(parsed_set: Vec<JsonSet>) -> T {
-        let mut final_set = T::default();
+    fn expand_into(parsed_set: Vec<JsonSet>, mut final_set: T, options: &ExpandOptions) -> T {
+        let parsed_set = options.constraints.constrain_input(parsed_set);
         let nb_bits = get_number_of_required_bits(&parsed_set);
         let parsed_set = parsed_set
             .iter()
             .map(|x| convert_itemset(&x.set, nb_bits))
             .collect::<Vec<_>>();
         for mut i in parsed_set {
-            Self::expand_one_solution_to_lower_level(&mut i, &mut final_set);
+            Self::expand_one_solution_to_lower_level(&mut i, &mut final_set, options);
         }
         final_set
     }
 
-    fn expand_one_solution_to_lower_level(solution: &mut Self::SolutionType, final_set: &mut T) {
-        let ones_length = solution.0.iter().filter(|x| **x).count();
-        if ones_length > 1 {
+    fn expand_one_solution_to_lower_level(
+        solution: &mut Self::SolutionType,
+        final_set: &mut T,
+        options: &ExpandOptions,
+    ) {
+        if options.is_below_min_utility(solution.0.iter_ones().map(|i| i as u8)) {
+            return;
+        }
+        let ones_length = solution.0.count_ones();
+        if ones_length > options.max_size {
+            let Ok(()) = descend_to_max_size(solution, ones_length, 0, options, &mut |x| {
+                if !final_set.set_contains(x) {
+                    Self::expand_one_solution_to_lower_level(x, final_set, options);
+                }
+                Ok::<(), Infallible>(())
+            });
+            return;
+        }
+        if ones_length > options.min_size {
             for i in 0..solution.0.len() {
-                if solution.0[i] {
+                if solution.0[i] && !options.constraints.is_required(i as u8) {
                     solution.0.set(i, false);
                     if !final_set.set_contains(solution) {
-                        Self::expand_one_solution_to_lower_level(solution, final_set);
+                        Self::expand_one_solution_to_lower_level(solution, final_set, options);
                     }
                     solution.0.set(i, true);
                 }
             }
         }
-        final_set.set_insert(solution.clone());
+        if ones_length >= options.min_size {
+            final_set.set_insert(solution.clone());
+        }
     }
 }
 
@@ -198,4 +220,21 @@ mod tests {
         let serialized_set = serde_json::to_string(&expanded_set).unwrap();
         assert_eq!(serialized_set.len(), 140);
     }
+
+    #[test]
+    fn test_2_empty_set() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![57, 58, 59, 60],
+            },
+            JsonSet { set: vec![60, 99] },
+        ];
+        let options = ExpandOptions {
+            min_size: 0,
+            ..ExpandOptions::default()
+        };
+        let expanded_set =
+            BitVecExpander::<FnvHashSet<WrappedBitVec>>::expand_with(parsed_set, &options);
+        assert_eq!(expanded_set.len(), 18);
+    }
 }
@@ -0,0 +1,113 @@
+pub(crate) use expander_rs::items::{parse_item_list, ItemMask};
+
+use crate::JsonSet;
+
+/// Item inclusion/exclusion constraints pushed into the expansion.
+/// Forbidden items and at-most-one groups are applied to the input item-sets, so the
+/// excluded parts of the lattice are never generated, and the expanders never remove
+/// a required item while descending.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Constraints {
+    pub(crate) required: ItemMask,
+    pub(crate) forbidden: ItemMask,
+    pub(crate) at_most_one: Vec<ItemMask>,
+}
+
+impl Constraints {
+    #[inline]
+    pub(crate) fn is_required(&self, item: u8) -> bool {
+        self.required.contains(item)
+    }
+
+    /// Whether an item-set satisfies the forbidden items and the at-most-one groups.
+    pub(crate) fn admits(&self, itemset: &ItemMask) -> bool {
+        !self.forbidden.intersects(itemset)
+            && self
+                .at_most_one
+                .iter()
+                .all(|group| group.intersection(itemset).len() <= 1)
+    }
+
+    fn is_unconstrained(&self) -> bool {
+        self.required.is_empty() && self.forbidden.is_empty() && self.at_most_one.is_empty()
+    }
+
+    /// Rewrites the input item-sets so that their downward closures only hold item-sets
+    /// satisfying the constraints (up to the required items kept by the expanders).
+    pub(crate) fn constrain_input(&self, parsed_set: Vec<JsonSet>) -> Vec<JsonSet> {
+        if self.is_unconstrained() {
+            return parsed_set;
+        }
+        let mut constrained_set = Vec::with_capacity(parsed_set.len());
+        for mut i in parsed_set {
+            i.set.retain(|&x| !self.forbidden.contains(x));
+            let required_len = i.set.iter().filter(|&&x| self.is_required(x)).count();
+            if required_len == self.required.len() {
+                self.split_groups(i.set, 0, &mut constrained_set);
+            }
+        }
+        constrained_set
+    }
+
+    /// Splits `solution` into one item-set per item kept from each group it overlaps.
+    fn split_groups(&self, solution: Vec<u8>, group: usize, constrained_set: &mut Vec<JsonSet>) {
+        let Some(mask) = self.at_most_one.get(group) else {
+            constrained_set.push(JsonSet { set: solution });
+            return;
+        };
+        let in_group = solution
+            .iter()
+            .copied()
+            .filter(|&x| mask.contains(x))
+            .collect::<Vec<_>>();
+        if in_group.len() <= 1 {
+            return self.split_groups(solution, group + 1, constrained_set);
+        }
+        let required_in_group = in_group
+            .iter()
+            .copied()
+            .filter(|&x| self.is_required(x))
+            .collect::<Vec<_>>();
+        let kept_items = match required_in_group.len() {
+            0 => in_group,
+            1 => required_in_group,
+            // two required items of the same group can never appear together.
+            _ => return,
+        };
+        for kept in kept_items {
+            let set = solution
+                .iter()
+                .copied()
+                .filter(|&x| x == kept || !mask.contains(x))
+                .collect();
+            self.split_groups(set, group + 1, constrained_set);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_constrain_input() {
+        let constraints = Constraints {
+            required: [1].into_iter().collect(),
+            forbidden: [9].into_iter().collect(),
+            at_most_one: vec![[2, 3].into_iter().collect()],
+        };
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![1, 2, 3, 9],
+            },
+            JsonSet { set: vec![2, 3, 4] },
+        ];
+        let constrained_set = constraints
+            .constrain_input(parsed_set)
+            .into_iter()
+            .map(|x| x.set)
+            .collect::<Vec<_>>();
+        assert_eq!(constrained_set, vec![vec![1, 2], vec![1, 3]]);
+    }
+}
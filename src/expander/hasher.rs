@@ -0,0 +1,263 @@
+use ahash::AHasher;
+use siphasher::sip::SipHasher13;
+use std::collections::HashSet;
+use std::hash::{BuildHasher, BuildHasherDefault, Hasher};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Seed used by the explicitly seeded hashers. Set once from the CLI before expanding.
+static HASH_SEED: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn set_hash_seed(seed: u64) {
+    HASH_SEED.store(seed, Ordering::Relaxed);
+}
+
+#[inline]
+fn hash_seed() -> u64 {
+    HASH_SEED.load(Ordering::Relaxed)
+}
+
+/// Pass-through hasher for keys which are already hashes (e.g. u64 fingerprints).
+#[derive(Default, Clone, Copy)]
+pub(crate) struct IdentityHasher(u64);
+
+impl Hasher for IdentityHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        // only meant for integer keys, fold anything else in.
+        for &b in bytes {
+            self.0 = self.0.rotate_left(8) ^ b as u64;
+        }
+    }
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.0 = i;
+    }
+    #[inline]
+    fn write_u128(&mut self, i: u128) {
+        self.0 = i as u64;
+    }
+}
+
+pub(crate) type IdentityHashSet<T> = HashSet<T, BuildHasherDefault<IdentityHasher>>;
+
+/// SipHash-1-3 with keys taken from the `--hash-seed`.
+pub(crate) struct SeededSipHasher(SipHasher13);
+
+impl SeededSipHasher {
+    #[inline]
+    fn with_seed(seed: u64) -> Self {
+        SeededSipHasher(SipHasher13::new_with_keys(seed, seed))
+    }
+}
+
+impl Default for SeededSipHasher {
+    #[inline]
+    fn default() -> Self {
+        Self::with_seed(hash_seed())
+    }
+}
+
+impl Hasher for SeededSipHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes)
+    }
+}
+
+/// AHash with keys taken from the `--hash-seed` instead of random ones.
+pub(crate) struct SeededAHasher(AHasher);
+
+impl SeededAHasher {
+    #[inline]
+    fn with_seed(seed: u64) -> Self {
+        let state = ahash::RandomState::with_seeds(seed, seed, seed, seed);
+        SeededAHasher(state.build_hasher())
+    }
+}
+
+impl Default for SeededAHasher {
+    #[inline]
+    fn default() -> Self {
+        Self::with_seed(hash_seed())
+    }
+}
+
+impl Hasher for SeededAHasher {
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes)
+    }
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.0.write_u64(i)
+    }
+}
+
+pub(crate) type Xxh3HashSet<T> = HashSet<T, BuildHasherDefault<xxhash_rust::xxh3::Xxh3>>;
+pub(crate) type WyHashSet<T> = HashSet<T, BuildHasherDefault<wyhash::WyHash>>;
+pub(crate) type SeededSipHashSet<T> = HashSet<T, BuildHasherDefault<SeededSipHasher>>;
+pub(crate) type SeededAHashSet<T> = HashSet<T, BuildHasherDefault<SeededAHasher>>;
+
+/// Registry of the hashers/backends which can be selected by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HasherKind {
+    Fnv,
+    Fx,
+    Std,
+    AHash,
+    Table,
+    Xxh3,
+    WyHash,
+    SeededSip,
+    SeededAHash,
+}
+
+impl HasherKind {
+    pub(crate) const ALL: [HasherKind; 9] = [
+        HasherKind::Fnv,
+        HasherKind::Fx,
+        HasherKind::Std,
+        HasherKind::AHash,
+        HasherKind::Table,
+        HasherKind::Xxh3,
+        HasherKind::WyHash,
+        HasherKind::SeededSip,
+        HasherKind::SeededAHash,
+    ];
+
+    pub(crate) const NAMES: [&'static str; 9] = [
+        "fnv",
+        "fx",
+        "std",
+        "aes",
+        "table",
+        "xxh3",
+        "wyhash",
+        "seeded-sip",
+        "seeded-aes",
+    ];
+
+    pub(crate) fn name(self) -> &'static str {
+        Self::NAMES[Self::ALL.iter().position(|&h| h == self).unwrap()]
+    }
+}
+
+impl FromStr for HasherKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::NAMES
+            .iter()
+            .position(|&name| name == s)
+            .map(|i| Self::ALL[i])
+            .ok_or_else(|| format!("unknown hasher '{}', use one of {:?}", s, Self::NAMES))
+    }
+}
+
+impl std::fmt::Display for HasherKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Runs `$body` with `$set` bound to the HashSet type and `$hasher` to the Hasher of `$kind`.
+macro_rules! with_hash_set {
+    ($kind:expr, $set:ident, $hasher:ident => $body:expr) => {
+        match $kind {
+            $crate::expander::hasher::HasherKind::Fnv => {
+                type $set<T> = fnv::FnvHashSet<T>;
+                type $hasher = fnv::FnvHasher;
+                $body
+            }
+            $crate::expander::hasher::HasherKind::Fx => {
+                type $set<T> = fxhash::FxHashSet<T>;
+                type $hasher = fxhash::FxHasher;
+                $body
+            }
+            $crate::expander::hasher::HasherKind::Std => {
+                type $set<T> = std::collections::HashSet<T>;
+                type $hasher = std::collections::hash_map::DefaultHasher;
+                $body
+            }
+            $crate::expander::hasher::HasherKind::AHash => {
+                type $set<T> = $crate::expander::set::WrappedAHashSet<T>;
+                type $hasher = ahash::AHasher;
+                $body
+            }
+            $crate::expander::hasher::HasherKind::Xxh3 => {
+                type $set<T> = $crate::expander::hasher::Xxh3HashSet<T>;
+                type $hasher = xxhash_rust::xxh3::Xxh3;
+                $body
+            }
+            $crate::expander::hasher::HasherKind::WyHash => {
+                type $set<T> = $crate::expander::hasher::WyHashSet<T>;
+                type $hasher = wyhash::WyHash;
+                $body
+            }
+            $crate::expander::hasher::HasherKind::SeededSip => {
+                type $set<T> = $crate::expander::hasher::SeededSipHashSet<T>;
+                type $hasher = $crate::expander::hasher::SeededSipHasher;
+                $body
+            }
+            $crate::expander::hasher::HasherKind::SeededAHash => {
+                type $set<T> = $crate::expander::hasher::SeededAHashSet<T>;
+                type $hasher = $crate::expander::hasher::SeededAHasher;
+                $body
+            }
+            $crate::expander::hasher::HasherKind::Table => {
+                anyhow::bail!(
+                    "Int Table only supports the Bit Manipulator and Hash-only Vec Expanders"
+                )
+            }
+        }
+    };
+}
+
+pub(crate) use with_hash_set;
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_identity_pass_through() {
+        let mut hasher = IdentityHasher::default();
+        hasher.write_u64(0xDEAD_BEEF);
+        assert_eq!(hasher.finish(), 0xDEAD_BEEF);
+    }
+
+    #[test]
+    fn test_registry_names() {
+        for kind in HasherKind::ALL {
+            assert_eq!(kind.name().parse::<HasherKind>(), Ok(kind));
+        }
+        assert!("md5".parse::<HasherKind>().is_err());
+    }
+
+    #[test]
+    fn test_seeded_hashers_depend_on_seed() {
+        let hash = |seed| {
+            let mut sip = SeededSipHasher::with_seed(seed);
+            sip.write_u64(42);
+            let mut aes = SeededAHasher::with_seed(seed);
+            aes.write_u64(42);
+            (sip.finish(), aes.finish())
+        };
+        assert_eq!(hash(7), hash(7));
+        assert_ne!(hash(7), hash(8));
+    }
+}
@@ -0,0 +1,127 @@
+use anyhow::Result;
+
+use crate::expander::constraint::ItemMask;
+use crate::expander::{ExpandOptions, SetLike};
+use crate::JsonSet;
+
+/// Closure of the input item-sets under pairwise intersection, which yields the closed
+/// item-sets of a context from its rows. Each input is intersected with every item-set
+/// found so far, and the new intersections are deduplicated with the `SetLike` backend.
+pub(crate) struct IntersectionClosure<T> {
+    _phantom: std::marker::PhantomData<T>,
+}
+
+impl<T> IntersectionClosure<T>
+where
+    T: Default,
+    T: SetLike<ItemMask>,
+{
+    /// Hands every distinct intersection to `sink` as soon as it is found and returns their
+    /// number. Intersections only get smaller, so the ones below `min_size` or without a
+    /// required item are dropped with everything they would generate. The other bounds and
+    /// constraints only filter the output.
+    pub(crate) fn expand_streaming(
+        parsed_set: Vec<JsonSet>,
+        options: &ExpandOptions,
+        sink: &mut impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<usize> {
+        let is_kept = |itemset: &ItemMask| {
+            itemset.len() >= options.min_size
+                && options.constraints.required.is_subset(itemset)
+                && !options.is_below_min_utility(itemset.items())
+        };
+        let mut closed_set = T::default();
+        let mut family = Vec::<ItemMask>::new();
+        let mut len = 0;
+        for i in parsed_set {
+            let input = i.set.iter().copied().collect::<ItemMask>();
+            let intersections = family
+                .iter()
+                .map(|itemset| itemset.intersection(&input))
+                .chain(std::iter::once(input))
+                .collect::<Vec<_>>();
+            for itemset in intersections {
+                if !is_kept(&itemset) || !closed_set.set_insert(itemset) {
+                    continue;
+                }
+                family.push(itemset);
+                if itemset.len() <= options.max_size && options.constraints.admits(&itemset) {
+                    sink(&itemset.items())?;
+                    len += 1;
+                }
+            }
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use fnv::FnvHashSet;
+
+    use crate::expander::constraint::Constraints;
+    use crate::expander::set::WrappedAHashSet;
+
+    use super::*;
+
+    fn close<T: Default + SetLike<ItemMask>>(
+        parsed_set: Vec<JsonSet>,
+        options: &ExpandOptions,
+    ) -> Vec<Vec<u8>> {
+        let mut itemsets = Vec::new();
+        let len = IntersectionClosure::<T>::expand_streaming(parsed_set, options, &mut |x| {
+            itemsets.push(x.to_vec());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(len, itemsets.len());
+        itemsets.sort();
+        itemsets
+    }
+
+    fn parsed_set() -> Vec<JsonSet> {
+        vec![
+            JsonSet {
+                set: vec![1, 2, 3, 4],
+            },
+            JsonSet { set: vec![2, 3, 5] },
+            JsonSet { set: vec![3, 4, 5] },
+            JsonSet { set: vec![6] },
+        ]
+    }
+
+    #[test]
+    fn test_1_fnv() {
+        let itemsets = close::<FnvHashSet<ItemMask>>(parsed_set(), &ExpandOptions::default());
+        assert_eq!(
+            itemsets,
+            vec![
+                vec![1, 2, 3, 4],
+                vec![2, 3],
+                vec![2, 3, 5],
+                vec![3],
+                vec![3, 4],
+                vec![3, 4, 5],
+                vec![3, 5],
+                vec![6],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_1_ahash_constraints() {
+        let options = ExpandOptions {
+            min_size: 2,
+            max_size: 3,
+            constraints: Constraints {
+                required: [3].into_iter().collect(),
+                forbidden: [2].into_iter().collect(),
+                ..Constraints::default()
+            },
+            ..ExpandOptions::default()
+        };
+        let itemsets = close::<WrappedAHashSet<ItemMask>>(parsed_set(), &options);
+        assert_eq!(itemsets, vec![vec![3, 4], vec![3, 4, 5], vec![3, 5]]);
+    }
+}
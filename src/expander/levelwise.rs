@@ -0,0 +1,192 @@
+use anyhow::Result;
+
+use crate::expander::{ExpandOptions, SetLike};
+use crate::JsonSet;
+
+/// Breadth-first Vec expansion. Item-sets of size k-1 are generated from the ones of size k
+/// and deduplicated only within their level. Each level is streamed to the sink and freed
+/// before the next one, so peak memory is bounded by the two largest adjacent levels.
+pub(crate) struct LevelWiseExpander<T> {
+    _phantom: std::marker::PhantomData<T>,
+}
+
+/// Number of item-sets of an expansion and of its largest level.
+pub(crate) struct LevelWiseStats {
+    pub(crate) len: usize,
+    pub(crate) largest_level_len: usize,
+}
+
+impl<T> LevelWiseExpander<T>
+where
+    T: Default,
+    T: SetLike<Vec<u8>>,
+    T: IntoIterator<Item = Vec<u8>>,
+{
+    pub(crate) fn expand_streaming(
+        parsed_set: Vec<JsonSet>,
+        options: &ExpandOptions,
+        sink: &mut impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<LevelWiseStats> {
+        let parsed_set = options.constraints.constrain_input(parsed_set);
+        let max_length = parsed_set.iter().map(|x| x.set.len()).max().unwrap_or(0);
+        let top_length = max_length.min(options.max_size);
+        let mut inputs_by_length = vec![Vec::new(); top_length + 1];
+        let mut longer_inputs = Vec::new();
+        for i in parsed_set {
+            match i.set.len() > top_length {
+                true => longer_inputs.push(i.set),
+                false => inputs_by_length[i.set.len()].push(i.set),
+            }
+        }
+        let mut stats = LevelWiseStats {
+            len: 0,
+            largest_level_len: 0,
+        };
+        let mut upper_level = T::default();
+        for length in (options.min_size..=top_length).rev() {
+            let mut level = T::default();
+            let mut level_len = 0;
+            let mut insert = |level: &mut T, itemset: Vec<u8>| -> Result<()> {
+                if options.is_below_min_utility(itemset.iter().copied()) {
+                    return Ok(());
+                }
+                if !level.set_contains(&itemset) {
+                    sink(&itemset)?;
+                    level.set_insert(itemset);
+                    level_len += 1;
+                }
+                Ok(())
+            };
+            for solution in std::mem::take(&mut inputs_by_length[length]) {
+                insert(&mut level, solution)?;
+            }
+            // item-sets above max_size are never stored, they only seed the top level.
+            for mut solution in std::mem::take(&mut longer_inputs) {
+                let mut result = Ok(());
+                for_each_subset_of_length(&mut solution, 0, length, options, &mut |subset| {
+                    if result.is_ok() {
+                        result = insert(&mut level, subset.to_vec());
+                    }
+                });
+                result?;
+            }
+            for mut solution in upper_level {
+                for i in 0..solution.len() {
+                    if options.constraints.is_required(solution[i]) {
+                        continue;
+                    }
+                    let el = solution.remove(i);
+                    insert(&mut level, solution.clone())?;
+                    solution.insert(i, el);
+                }
+            }
+            stats.len += level_len;
+            stats.largest_level_len = stats.largest_level_len.max(level_len);
+            upper_level = level;
+        }
+        Ok(stats)
+    }
+}
+
+/// Calls `f` once for every subset of `solution` with `length` items which keeps the required items.
+fn for_each_subset_of_length(
+    solution: &mut Vec<u8>,
+    start: usize,
+    length: usize,
+    options: &ExpandOptions,
+    f: &mut impl FnMut(&[u8]),
+) {
+    if options.is_below_min_utility(solution.iter().copied()) {
+        return;
+    }
+    if solution.len() == length {
+        f(solution);
+        return;
+    }
+    for i in start..solution.len() {
+        if options.constraints.is_required(solution[i]) {
+            continue;
+        }
+        let el = solution.remove(i);
+        for_each_subset_of_length(solution, i, length, options, f);
+        solution.insert(i, el);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use fnv::FnvHashSet;
+
+    use crate::expander::set::WrappedAHashSet;
+
+    use super::*;
+
+    fn expand_all<T>(
+        parsed_set: Vec<JsonSet>,
+        options: &ExpandOptions,
+    ) -> (Vec<Vec<u8>>, LevelWiseStats)
+    where
+        T: Default + SetLike<Vec<u8>> + IntoIterator<Item = Vec<u8>>,
+    {
+        let mut itemsets = Vec::new();
+        let stats = LevelWiseExpander::<T>::expand_streaming(parsed_set, options, &mut |itemset| {
+            itemsets.push(itemset.to_vec());
+            Ok(())
+        })
+        .unwrap();
+        (itemsets, stats)
+    }
+
+    #[test]
+    fn test_1_fnv() {
+        let parsed_set = vec![
+            JsonSet { set: vec![1, 2, 3] },
+            JsonSet { set: vec![4, 5, 6] },
+        ];
+        let (itemsets, stats) =
+            expand_all::<FnvHashSet<Vec<u8>>>(parsed_set, &ExpandOptions::default());
+        assert_eq!(stats.len, 14);
+        assert_eq!(stats.largest_level_len, 6);
+        assert_eq!(itemsets.len(), 14);
+        // streamed from the largest item-sets down to the singletons.
+        assert!(itemsets.windows(2).all(|w| w[0].len() >= w[1].len()));
+    }
+
+    #[test]
+    fn test_2_fnv() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![57, 58, 59, 60],
+            },
+            JsonSet { set: vec![60, 99] },
+        ];
+        let (itemsets, stats) =
+            expand_all::<FnvHashSet<Vec<u8>>>(parsed_set, &ExpandOptions::default());
+        assert_eq!(stats.len, 17);
+        assert_eq!(itemsets.len(), 17);
+    }
+
+    #[test]
+    fn test_1_ahash() {
+        let parsed_set = vec![
+            JsonSet { set: vec![1, 2, 3] },
+            JsonSet { set: vec![4, 5, 6] },
+        ];
+        let (_, stats) =
+            expand_all::<WrappedAHashSet<Vec<u8>>>(parsed_set, &ExpandOptions::default());
+        assert_eq!(stats.len, 14);
+    }
+    #[test]
+    fn test_2_ahash() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![57, 58, 59, 60],
+            },
+            JsonSet { set: vec![60, 99] },
+        ];
+        let (_, stats) =
+            expand_all::<WrappedAHashSet<Vec<u8>>>(parsed_set, &ExpandOptions::default());
+        assert_eq!(stats.len, 17);
+    }
+}
@@ -1,12 +1,60 @@
 pub(crate) mod bitman;
 pub(crate) mod bitvec;
+pub(crate) mod constraint;
+pub(crate) mod hasher;
+pub(crate) mod intersection;
+pub(crate) mod levelwise;
+pub(crate) mod multiset;
+pub(crate) mod prune;
+pub(crate) mod remap;
+pub(crate) mod sequence;
 pub(crate) mod set;
+pub(crate) mod table;
+pub(crate) mod taxonomy;
+pub(crate) mod utility;
 pub(crate) mod vec;
 pub(crate) mod vechashonly;
 
 use crate::JsonSet;
+use constraint::Constraints;
 use set::SetLike;
 use std::hash::Hash;
+use utility::MinUtility;
+
+/// Bounds applied while expanding instead of filtering the final set.
+#[derive(Debug, Clone)]
+pub(crate) struct ExpandOptions {
+    /// Smallest item-set size kept; the search stops descending below it.
+    /// 0 adds the empty item-set.
+    pub(crate) min_size: usize,
+    /// Largest item-set size kept; larger ones are walked through without being stored.
+    pub(crate) max_size: usize,
+    /// Required, forbidden and at-most-one items.
+    pub(crate) constraints: Constraints,
+    /// Smallest summed item weight kept; the search stops descending below it.
+    pub(crate) min_utility: Option<MinUtility>,
+}
+
+impl Default for ExpandOptions {
+    fn default() -> Self {
+        ExpandOptions {
+            min_size: 1,
+            max_size: usize::MAX,
+            constraints: Constraints::default(),
+            min_utility: None,
+        }
+    }
+}
+
+impl ExpandOptions {
+    /// Whether the item-set, and so every subset of it, weighs less than `min_utility`.
+    #[inline]
+    pub(crate) fn is_below_min_utility(&self, items: impl IntoIterator<Item = u8>) -> bool {
+        self.min_utility
+            .as_ref()
+            .is_some_and(|min_utility| !min_utility.admits(items))
+    }
+}
 
 pub(crate) trait Expander
 where
@@ -17,9 +65,187 @@ where
     type SolutionType;
     type SetType;
     type HashType;
-    fn expand(parsed_set: Vec<JsonSet>) -> Self::SetType;
+    #[cfg(test)]
+    fn expand(parsed_set: Vec<JsonSet>) -> Self::SetType {
+        Self::expand_with(parsed_set, &ExpandOptions::default())
+    }
+    fn expand_with(parsed_set: Vec<JsonSet>, options: &ExpandOptions) -> Self::SetType {
+        Self::expand_into(parsed_set, Self::SetType::default(), options)
+    }
+    /// Expands into an already created set, e.g. one pre-sized for the expected output.
+    fn expand_into(
+        parsed_set: Vec<JsonSet>,
+        final_set: Self::SetType,
+        options: &ExpandOptions,
+    ) -> Self::SetType;
     fn expand_one_solution_to_lower_level(
         solution: &mut Self::SolutionType,
         final_set: &mut Self::SetType,
+        options: &ExpandOptions,
     );
 }
+
+/// Solution the expanders take items away from one at a time.
+pub(crate) trait Shrinkable {
+    /// What `restore` needs to put a taken item back.
+    type Taken;
+    /// Items may be taken at the positions `0..nb_positions()`.
+    fn nb_positions(&self) -> usize;
+    /// Items of the solution, repeated ones included, for the minimum utility.
+    fn items(&self) -> impl Iterator<Item = u8> + '_;
+    /// Takes the item at `position` away unless there is none or the constraints keep it,
+    /// returns it with the position the walk goes on from.
+    fn take(&mut self, position: usize, constraints: &Constraints) -> Option<(Self::Taken, usize)>;
+    /// Undoes `take`.
+    fn restore(&mut self, position: usize, taken: Self::Taken);
+}
+
+impl Shrinkable for Vec<u8> {
+    type Taken = u8;
+
+    fn nb_positions(&self) -> usize {
+        self.len()
+    }
+
+    fn items(&self) -> impl Iterator<Item = u8> + '_ {
+        self.iter().copied()
+    }
+
+    fn take(&mut self, position: usize, constraints: &Constraints) -> Option<(u8, usize)> {
+        if constraints.is_required(self[position]) {
+            return None;
+        }
+        Some((self.remove(position), position))
+    }
+
+    fn restore(&mut self, position: usize, taken: u8) {
+        self.insert(position, taken);
+    }
+}
+
+/// Walks down from `solution`, of `size` items above `max_size`, and hands its subsets of
+/// `max_size` items to `visit`. Nothing above `max_size` is stored, so there is no set to
+/// prune with; taking items only at or after the position the last take went on from
+/// reaches every subset exactly once (at least once for sequences with repeated items).
+pub(crate) fn descend_to_max_size<S: Shrinkable, E>(
+    solution: &mut S,
+    size: usize,
+    start: usize,
+    options: &ExpandOptions,
+    visit: &mut impl FnMut(&mut S) -> Result<(), E>,
+) -> Result<(), E> {
+    if options.is_below_min_utility(solution.items()) {
+        return Ok(());
+    }
+    if size == options.max_size {
+        return visit(solution);
+    }
+    for position in start..solution.nb_positions() {
+        let Some((taken, next)) = solution.take(position, &options.constraints) else {
+            continue;
+        };
+        descend_to_max_size(solution, size - 1, next, options, visit)?;
+        solution.restore(position, taken);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use fnv::{FnvHashSet, FnvHasher};
+
+    use crate::expander::bitman::BitManipulatorExpander;
+    use crate::expander::bitvec::BitVecExpander;
+    use crate::expander::levelwise::LevelWiseExpander;
+    use crate::expander::set::{WrappedBitVec, Wrappedu128};
+    use crate::expander::table::IntTable;
+    use crate::expander::vec::VecExpander;
+    use crate::expander::vechashonly::VecHashOnlyExpander;
+
+    use super::*;
+
+    /// Number of item-sets found by each item-set expander, all walking down the same way
+    /// above `max_size`.
+    fn expanded_lens(options: &ExpandOptions) -> Vec<usize> {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![1, 2, 3, 4],
+            },
+            JsonSet { set: vec![4, 5, 6] },
+        ];
+        vec![
+            VecExpander::<FnvHashSet<Vec<u8>>>::expand_with(parsed_set.clone(), options).len(),
+            VecHashOnlyExpander::<FnvHashSet<u64>, FnvHasher>::expand_with(
+                parsed_set.clone(),
+                options,
+            )
+            .len(),
+            BitManipulatorExpander::<FnvHashSet<Wrappedu128>>::expand_with(
+                parsed_set.clone(),
+                options,
+            )
+            .len(),
+            BitManipulatorExpander::<IntTable<Wrappedu128>>::expand_with(
+                parsed_set.clone(),
+                options,
+            )
+            .len(),
+            BitVecExpander::<FnvHashSet<WrappedBitVec>>::expand_with(parsed_set.clone(), options)
+                .len(),
+            LevelWiseExpander::<FnvHashSet<Vec<u8>>>::expand_streaming(
+                parsed_set,
+                options,
+                &mut |_| Ok(()),
+            )
+            .unwrap()
+            .len,
+        ]
+    }
+
+    #[test]
+    fn test_size_bounds() {
+        let options = ExpandOptions {
+            min_size: 2,
+            max_size: 3,
+            ..ExpandOptions::default()
+        };
+        // 4 + 6 from the first and 1 + 3 from the second item-set, {4} is not kept.
+        assert_eq!(expanded_lens(&options), vec![14; 6]);
+    }
+
+    #[test]
+    fn test_constraints() {
+        let options = ExpandOptions {
+            constraints: Constraints {
+                required: [4].into_iter().collect(),
+                forbidden: [6].into_iter().collect(),
+                at_most_one: vec![[1, 2].into_iter().collect()],
+            },
+            ..ExpandOptions::default()
+        };
+        assert_eq!(expanded_lens(&options), vec![7; 6]);
+    }
+
+    #[test]
+    fn test_descend_to_max_size() {
+        let options = ExpandOptions {
+            max_size: 2,
+            constraints: Constraints {
+                required: [3].into_iter().collect(),
+                ..Constraints::default()
+            },
+            ..ExpandOptions::default()
+        };
+        let mut visited = Vec::new();
+        let mut solution = vec![1, 2, 3, 4];
+        descend_to_max_size(&mut solution, 4, 0, &options, &mut |x| {
+            visited.push(x.clone());
+            Ok::<(), ()>(())
+        })
+        .unwrap();
+        assert_eq!(solution, vec![1, 2, 3, 4]);
+        visited.sort();
+        assert_eq!(visited, vec![vec![1, 3], vec![2, 3], vec![3, 4]]);
+    }
+}
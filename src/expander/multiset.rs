@@ -0,0 +1,259 @@
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+use crate::expander::constraint::{Constraints, ItemMask};
+use crate::expander::{descend_to_max_size, ExpandOptions, SetLike, Shrinkable};
+use crate::JsonSet;
+
+/// Bag of items as (item, count) pairs sorted by item, every count at least 1.
+/// Serializes as `[[item, count], ...]`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize)]
+pub(crate) struct Multiset(Vec<(u8, u8)>);
+
+impl Multiset {
+    /// Counts the repeated items of `items`, e.g. `[1, 2, 1]` is `[[1, 2], [2, 1]]`.
+    pub(crate) fn from_items(items: &[u8]) -> Result<Self> {
+        let mut counts = [0usize; u8::MAX as usize + 1];
+        for &item in items {
+            counts[item as usize] += 1;
+        }
+        let mut multiset = Vec::new();
+        for (item, &count) in counts.iter().enumerate() {
+            if count > u8::MAX as usize {
+                bail!(
+                    "Item {} is repeated {} times, at most 255 are supported",
+                    item,
+                    count
+                );
+            }
+            if count > 0 {
+                multiset.push((item as u8, count as u8));
+            }
+        }
+        Ok(Multiset(multiset))
+    }
+
+    /// Total number of units.
+    pub(crate) fn size(&self) -> usize {
+        self.0.iter().map(|&(_, count)| count as usize).sum()
+    }
+
+    /// Items repeated by their counts.
+    pub(crate) fn items(&self) -> impl Iterator<Item = u8> + '_ {
+        self.0
+            .iter()
+            .flat_map(|&(item, count)| std::iter::repeat_n(item, count as usize))
+    }
+
+    fn distinct_items(&self) -> ItemMask {
+        self.0.iter().map(|&(item, _)| item).collect()
+    }
+
+    /// Takes one unit of the item at `position` away and returns whether its pair went away.
+    fn decrement(&mut self, position: usize) -> bool {
+        self.0[position].1 -= 1;
+        let removed = self.0[position].1 == 0;
+        if removed {
+            self.0.remove(position);
+        }
+        removed
+    }
+
+    /// Undoes `decrement`.
+    fn increment(&mut self, position: usize, item: u8, removed: bool) {
+        match removed {
+            true => self.0.insert(position, (item, 1)),
+            false => self.0[position].1 += 1,
+        }
+    }
+}
+
+/// Takes units away, the last one of a required item excepted.
+impl Shrinkable for Multiset {
+    type Taken = (u8, bool);
+
+    fn nb_positions(&self) -> usize {
+        self.0.len()
+    }
+
+    fn items(&self) -> impl Iterator<Item = u8> + '_ {
+        Multiset::items(self)
+    }
+
+    fn take(&mut self, position: usize, constraints: &Constraints) -> Option<((u8, bool), usize)> {
+        let (item, count) = self.0[position];
+        if count == 1 && constraints.is_required(item) {
+            return None;
+        }
+        Some(((item, self.decrement(position)), position))
+    }
+
+    fn restore(&mut self, position: usize, (item, removed): (u8, bool)) {
+        self.increment(position, item, removed);
+    }
+}
+
+/// Downward closure of the input bags: every sub-bag, counts decreasing one unit at a time.
+/// Sizes are numbers of units.
+pub(crate) struct MultisetExpander<T> {
+    _phantom: std::marker::PhantomData<T>,
+}
+
+impl<T> MultisetExpander<T>
+where
+    T: Default,
+    T: SetLike<Multiset>,
+{
+    /// Hands every distinct sub-bag to `sink` the moment it is first inserted and returns
+    /// their number. Forbidden items are dropped from the inputs and the last unit of a
+    /// required item is never taken away; the at-most-one groups only filter the output.
+    pub(crate) fn expand_streaming(
+        parsed_set: Vec<JsonSet>,
+        options: &ExpandOptions,
+        sink: &mut impl FnMut(&Multiset) -> Result<()>,
+    ) -> Result<usize> {
+        let mut final_set = T::default();
+        let mut len = 0;
+        for mut i in parsed_set {
+            i.set
+                .retain(|&x| !options.constraints.forbidden.contains(x));
+            let mut solution = Multiset::from_items(&i.set)?;
+            if !options
+                .constraints
+                .required
+                .is_subset(&solution.distinct_items())
+            {
+                continue;
+            }
+            Self::expand_one_solution_to_lower_level(
+                &mut solution,
+                &mut final_set,
+                options,
+                sink,
+                &mut len,
+            )?;
+        }
+        Ok(len)
+    }
+
+    fn expand_one_solution_to_lower_level(
+        solution: &mut Multiset,
+        final_set: &mut T,
+        options: &ExpandOptions,
+        sink: &mut impl FnMut(&Multiset) -> Result<()>,
+        len: &mut usize,
+    ) -> Result<()> {
+        if options.is_below_min_utility(solution.items()) {
+            return Ok(());
+        }
+        let size = solution.size();
+        if size > options.max_size {
+            return descend_to_max_size(solution, size, 0, options, &mut |x| {
+                if !final_set.set_contains(x) {
+                    Self::expand_one_solution_to_lower_level(x, final_set, options, sink, len)?;
+                }
+                Ok(())
+            });
+        }
+        if size > options.min_size {
+            for i in 0..solution.0.len() {
+                let (item, count) = solution.0[i];
+                if count == 1 && options.constraints.is_required(item) {
+                    continue;
+                }
+                let removed = solution.decrement(i);
+                if !final_set.set_contains(solution) {
+                    Self::expand_one_solution_to_lower_level(
+                        solution, final_set, options, sink, len,
+                    )?;
+                }
+                solution.increment(i, item, removed);
+            }
+        }
+        if size >= options.min_size
+            && final_set.set_insert(solution.clone())
+            && options.constraints.admits(&solution.distinct_items())
+        {
+            sink(solution)?;
+            *len += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use fnv::FnvHashSet;
+
+    use crate::expander::constraint::Constraints;
+
+    use super::*;
+
+    fn expand(parsed_set: Vec<JsonSet>, options: &ExpandOptions) -> Vec<Vec<(u8, u8)>> {
+        let mut multisets = Vec::new();
+        let len = MultisetExpander::<FnvHashSet<Multiset>>::expand_streaming(
+            parsed_set,
+            options,
+            &mut |x| {
+                multisets.push(x.0.clone());
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(len, multisets.len());
+        multisets.sort();
+        multisets
+    }
+
+    #[test]
+    fn test_from_items() {
+        let multiset = Multiset::from_items(&[2, 1, 2]).unwrap();
+        assert_eq!(multiset, Multiset(vec![(1, 1), (2, 2)]));
+        assert_eq!(multiset.size(), 3);
+        assert_eq!(serde_json::to_string(&multiset).unwrap(), "[[1,1],[2,2]]");
+        assert!(Multiset::from_items(&[7; 256]).is_err());
+    }
+
+    #[test]
+    fn test_1_multiset() {
+        // milk (1) x2 and bread (2), with a sub-bag of another basket.
+        let parsed_set = vec![JsonSet { set: vec![1, 1, 2] }, JsonSet { set: vec![1, 2] }];
+        assert_eq!(
+            expand(parsed_set, &ExpandOptions::default()),
+            vec![
+                vec![(1, 1)],
+                vec![(1, 1), (2, 1)],
+                vec![(1, 2)],
+                vec![(1, 2), (2, 1)],
+                vec![(2, 1)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_1_multiset_bounds() {
+        let parsed_set = vec![JsonSet {
+            set: vec![1, 1, 1, 2, 2, 3],
+        }];
+        let options = ExpandOptions {
+            min_size: 2,
+            max_size: 3,
+            constraints: Constraints {
+                required: [3].into_iter().collect(),
+                ..Constraints::default()
+            },
+            ..ExpandOptions::default()
+        };
+        assert_eq!(
+            expand(parsed_set, &options),
+            vec![
+                vec![(1, 1), (2, 1), (3, 1)],
+                vec![(1, 1), (3, 1)],
+                vec![(1, 2), (3, 1)],
+                vec![(2, 1), (3, 1)],
+                vec![(2, 2), (3, 1)],
+            ]
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use crate::expander::constraint::ItemMask;
+use crate::JsonSet;
+
+/// Item-sets indexed by the bitset of those holding each item: an item-set is contained in
+/// those in the intersection of the bitsets of its items.
+#[derive(Debug, Default)]
+pub(crate) struct SupersetIndex {
+    len: usize,
+    containing: Vec<Vec<u64>>,
+}
+
+impl SupersetIndex {
+    pub(crate) fn insert(&mut self, itemset: &[u8]) {
+        if self.containing.is_empty() {
+            self.containing = vec![Vec::new(); u8::MAX as usize + 1];
+        }
+        let (word, bit) = (self.len / 64, self.len % 64);
+        for &item in itemset {
+            let words = &mut self.containing[item as usize];
+            words.resize(words.len().max(word + 1), 0);
+            words[word] |= 1 << bit;
+        }
+        self.len += 1;
+    }
+
+    /// Whether one of the indexed item-sets contains `itemset`.
+    pub(crate) fn has_superset(&self, itemset: &[u8]) -> bool {
+        (0..self.len.div_ceil(64)).any(|word| {
+            itemset.iter().fold(u64::MAX, |acc, &item| {
+                acc & self.containing[item as usize]
+                    .get(word)
+                    .copied()
+                    .unwrap_or(0)
+            }) != 0
+        })
+    }
+}
+
+/// Drops the input item-sets contained in another one (duplicates included), as their
+/// subsets are all generated from it, and orders the rest by decreasing size so that more
+/// of the subsets probed with `set_contains` are already in the set. Returns the kept
+/// item-sets and the number of dropped ones. Only the earlier, at least as large, item-sets
+/// can contain an item-set.
+pub(crate) fn prune_redundant_inputs(parsed_set: Vec<JsonSet>) -> (Vec<JsonSet>, usize) {
+    let nb_inputs = parsed_set.len();
+    let mut inputs = parsed_set
+        .into_iter()
+        .map(|x| (x.set.iter().copied().collect::<ItemMask>(), x))
+        .collect::<Vec<_>>();
+    // stable, so that inputs of the same size keep their order.
+    inputs.sort_by_key(|(mask, _)| std::cmp::Reverse(mask.len()));
+    let mut kept = Vec::with_capacity(nb_inputs);
+    let mut index = SupersetIndex::default();
+    for (mask, input) in inputs {
+        let items = mask.items();
+        if !index.has_superset(&items) {
+            index.insert(&items);
+            kept.push(input);
+        }
+    }
+    let nb_dropped = nb_inputs - kept.len();
+    (kept, nb_dropped)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_prune_redundant_inputs() {
+        let parsed_set = [
+            vec![2, 3],
+            vec![1, 2, 3],
+            vec![4, 5],
+            vec![3, 2],
+            vec![5],
+            vec![3, 4, 6, 7],
+            vec![],
+        ]
+        .into_iter()
+        .map(|set| JsonSet { set })
+        .collect();
+        let (kept, nb_dropped) = prune_redundant_inputs(parsed_set);
+        assert_eq!(nb_dropped, 4);
+        assert_eq!(
+            kept.into_iter().map(|x| x.set).collect::<Vec<_>>(),
+            vec![vec![3, 4, 6, 7], vec![1, 2, 3], vec![4, 5]]
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use crate::expander::constraint::{Constraints, ItemMask};
+use crate::expander::utility::MinUtility;
+use crate::expander::ExpandOptions;
+use crate::JsonSet;
+
+/// Dense ids `0..len` of the items actually used, so that sparse ids still fit the
+/// expanders with fixed-size keys (e.g. up to 128 items for the Bit Manipulator Expander).
+#[derive(Debug, Clone)]
+pub(crate) struct ItemRemap {
+    dense: Vec<Option<u8>>,
+    original: Vec<u8>,
+}
+
+impl ItemRemap {
+    /// Numbers the items of the input item-sets and the `extra` ones in increasing order, or
+    /// by decreasing number of occurrences in the inputs if `by_frequency` (ties in
+    /// increasing order).
+    pub(crate) fn new(
+        parsed_set: &[JsonSet],
+        extra: impl IntoIterator<Item = u8>,
+        by_frequency: bool,
+    ) -> Self {
+        let mut counts = [0usize; u8::MAX as usize + 1];
+        for &item in parsed_set.iter().flat_map(|x| &x.set) {
+            counts[item as usize] += 1;
+        }
+        let extra = extra.into_iter().collect::<ItemMask>();
+        let mut original = (0..=u8::MAX)
+            .filter(|&x| counts[x as usize] > 0 || extra.contains(x))
+            .collect::<Vec<_>>();
+        if by_frequency {
+            original.sort_by_key(|&x| std::cmp::Reverse(counts[x as usize]));
+        }
+        let mut dense = vec![None; u8::MAX as usize + 1];
+        for (id, &item) in original.iter().enumerate() {
+            dense[item as usize] = Some(id as u8);
+        }
+        ItemRemap { dense, original }
+    }
+
+    /// Number of distinct items, the dense ids are below it.
+    pub(crate) fn len(&self) -> usize {
+        self.original.len()
+    }
+
+    fn dense_mask(&self, mask: &ItemMask) -> ItemMask {
+        mask.items()
+            .into_iter()
+            .filter_map(|x| self.dense[x as usize])
+            .collect()
+    }
+
+    /// Input item-sets with dense ids, in increasing order.
+    pub(crate) fn remap_input(&self, parsed_set: Vec<JsonSet>) -> Vec<JsonSet> {
+        parsed_set
+            .into_iter()
+            .map(|x| {
+                let mut set = x
+                    .set
+                    .iter()
+                    .map(|&item| self.dense[item as usize].expect("items of the inputs are mapped"))
+                    .collect::<Vec<_>>();
+                set.sort_unstable();
+                JsonSet { set }
+            })
+            .collect()
+    }
+
+    /// Expansion options on dense ids. Forbidden and at-most-one items used nowhere are
+    /// dropped, the required ones are always mapped.
+    pub(crate) fn remap_options(&self, options: &ExpandOptions) -> ExpandOptions {
+        let constraints = &options.constraints;
+        ExpandOptions {
+            constraints: Constraints {
+                required: self.dense_mask(&constraints.required),
+                forbidden: self.dense_mask(&constraints.forbidden),
+                at_most_one: constraints
+                    .at_most_one
+                    .iter()
+                    .map(|group| self.dense_mask(group))
+                    .collect(),
+            },
+            min_utility: options.min_utility.as_ref().map(|x| MinUtility {
+                weights: x.weights.remapped(&self.original),
+                min_utility: x.min_utility,
+            }),
+            ..options.clone()
+        }
+    }
+
+    /// Item-set with the original ids, in increasing order.
+    pub(crate) fn original(&self, itemset: &[u8]) -> Vec<u8> {
+        let mut original = itemset
+            .iter()
+            .map(|&x| self.original[x as usize])
+            .collect::<Vec<_>>();
+        original.sort_unstable();
+        original
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_remap() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![200, 250],
+            },
+            JsonSet { set: vec![7, 250] },
+        ];
+        let remap = ItemRemap::new(&parsed_set, [9], false);
+        assert_eq!(remap.len(), 4);
+        let remapped = remap.remap_input(parsed_set.clone());
+        assert_eq!(remapped[0].set, vec![2, 3]);
+        assert_eq!(remap.original(&remapped[1].set), vec![7, 250]);
+        let remap = ItemRemap::new(&parsed_set, [], true);
+        let remapped = remap.remap_input(parsed_set);
+        assert_eq!(remapped[0].set, vec![0, 2]);
+        assert_eq!(remapped[1].set, vec![0, 1]);
+        assert_eq!(remap.original(&[2, 0]), vec![200, 250]);
+    }
+
+    #[test]
+    fn test_remap_options() {
+        let parsed_set = vec![JsonSet { set: vec![10, 20] }];
+        let options = ExpandOptions {
+            constraints: Constraints {
+                required: [20].into_iter().collect(),
+                forbidden: [10, 30].into_iter().collect(),
+                at_most_one: vec![[10, 20].into_iter().collect()],
+            },
+            ..ExpandOptions::default()
+        };
+        let remap = ItemRemap::new(&parsed_set, options.constraints.required.items(), false);
+        let constraints = remap.remap_options(&options).constraints;
+        assert_eq!(constraints.required.items(), vec![1]);
+        assert_eq!(constraints.forbidden.items(), vec![0]);
+        assert_eq!(constraints.at_most_one[0].items(), vec![0, 1]);
+    }
+}
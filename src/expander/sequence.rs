@@ -0,0 +1,221 @@
+use anyhow::Result;
+
+use crate::expander::constraint::{Constraints, ItemMask};
+use crate::expander::{descend_to_max_size, ExpandOptions, SetLike, Shrinkable};
+use crate::JsonSet;
+
+pub(crate) fn has_repeated_items(sequence: &[u8]) -> bool {
+    sequence.iter().copied().collect::<ItemMask>().len() != sequence.len()
+}
+
+/// Distinct subsequences of the input sequences, i.e. their items kept in input order and
+/// possibly repeated, or only their contiguous substrings.
+pub(crate) struct SequenceExpander<T> {
+    _phantom: std::marker::PhantomData<T>,
+}
+
+/// Sequence any item of which may be removed, the constraints only filtering the output.
+struct Subsequence<'a>(&'a mut Vec<u8>);
+
+impl Shrinkable for Subsequence<'_> {
+    type Taken = u8;
+
+    fn nb_positions(&self) -> usize {
+        self.0.len()
+    }
+
+    fn items(&self) -> impl Iterator<Item = u8> + '_ {
+        self.0.iter().copied()
+    }
+
+    fn take(&mut self, position: usize, _: &Constraints) -> Option<(u8, usize)> {
+        Some((self.0.remove(position), position))
+    }
+
+    fn restore(&mut self, position: usize, taken: u8) {
+        self.0.insert(position, taken);
+    }
+}
+
+/// Sequences found so far and where the new ones matching the constraints go.
+struct SequenceSink<'a, T, F> {
+    final_set: T,
+    options: &'a ExpandOptions,
+    sink: &'a mut F,
+    len: usize,
+}
+
+impl<T, F> SequenceSink<'_, T, F>
+where
+    T: SetLike<Vec<u8>>,
+    F: FnMut(&[u8]) -> Result<()>,
+{
+    fn insert(&mut self, sequence: &[u8]) -> Result<()> {
+        if !self.final_set.set_insert(sequence.to_vec()) {
+            return Ok(());
+        }
+        let mask = sequence.iter().copied().collect::<ItemMask>();
+        if self.options.constraints.required.is_subset(&mask)
+            && self.options.constraints.admits(&mask)
+        {
+            (self.sink)(sequence)?;
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<T> SequenceExpander<T>
+where
+    T: Default,
+    T: SetLike<Vec<u8>>,
+{
+    /// Hands every distinct subsequence (or substring if `contiguous`) to `sink` as soon as
+    /// it is found and returns their number. An item may repeat, so the constraints only
+    /// filter the output instead of rewriting the input like the item-set expanders.
+    pub(crate) fn expand_streaming(
+        parsed_set: Vec<JsonSet>,
+        contiguous: bool,
+        options: &ExpandOptions,
+        sink: &mut impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<usize> {
+        let mut sequence_sink = SequenceSink {
+            final_set: T::default(),
+            options,
+            sink,
+            len: 0,
+        };
+        for mut i in parsed_set {
+            match contiguous {
+                true => Self::expand_substrings(&i.set, &mut sequence_sink)?,
+                false => Self::expand_subsequences(&mut i.set, &mut sequence_sink)?,
+            }
+        }
+        Ok(sequence_sink.len)
+    }
+
+    fn expand_substrings(
+        sequence: &[u8],
+        sequence_sink: &mut SequenceSink<T, impl FnMut(&[u8]) -> Result<()>>,
+    ) -> Result<()> {
+        let options = sequence_sink.options;
+        for length in options.min_size..=options.max_size.min(sequence.len()) {
+            if length == 0 {
+                sequence_sink.insert(&[])?;
+                continue;
+            }
+            for substring in sequence.windows(length) {
+                if !options.is_below_min_utility(substring.iter().copied()) {
+                    sequence_sink.insert(substring)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Same walk as the Vec Expander, the item-sets being sequences.
+    fn expand_subsequences(
+        solution: &mut Vec<u8>,
+        sequence_sink: &mut SequenceSink<T, impl FnMut(&[u8]) -> Result<()>>,
+    ) -> Result<()> {
+        let options = sequence_sink.options;
+        if options.is_below_min_utility(solution.iter().copied()) {
+            return Ok(());
+        }
+        let length = solution.len();
+        if length > options.max_size {
+            return descend_to_max_size(&mut Subsequence(solution), length, 0, options, &mut |x| {
+                if !sequence_sink.final_set.set_contains(x.0) {
+                    Self::expand_subsequences(x.0, sequence_sink)?;
+                }
+                Ok(())
+            });
+        }
+        if length > options.min_size {
+            for i in 0..length {
+                // removing either of two equal neighbours gives the same subsequence.
+                if i > 0 && solution[i] == solution[i - 1] {
+                    continue;
+                }
+                let el = solution.remove(i);
+                if !sequence_sink.final_set.set_contains(solution) {
+                    Self::expand_subsequences(solution, sequence_sink)?;
+                }
+                solution.insert(i, el);
+            }
+        }
+        if length >= options.min_size {
+            sequence_sink.insert(solution)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use fnv::FnvHashSet;
+
+    use super::*;
+
+    fn expand(parsed_set: Vec<JsonSet>, contiguous: bool, options: &ExpandOptions) -> Vec<Vec<u8>> {
+        let mut sequences = Vec::new();
+        let len = SequenceExpander::<FnvHashSet<Vec<u8>>>::expand_streaming(
+            parsed_set,
+            contiguous,
+            options,
+            &mut |x| {
+                sequences.push(x.to_vec());
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(len, sequences.len());
+        sequences.sort();
+        sequences
+    }
+
+    #[test]
+    fn test_1_subsequences() {
+        let parsed_set = vec![JsonSet { set: vec![3, 1, 3] }, JsonSet { set: vec![1, 3] }];
+        assert_eq!(
+            expand(parsed_set, false, &ExpandOptions::default()),
+            vec![
+                vec![1],
+                vec![1, 3],
+                vec![3],
+                vec![3, 1],
+                vec![3, 1, 3],
+                vec![3, 3],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_1_substrings() {
+        let parsed_set = vec![JsonSet {
+            set: vec![2, 1, 2, 1],
+        }];
+        let options = ExpandOptions {
+            max_size: 3,
+            ..ExpandOptions::default()
+        };
+        assert_eq!(
+            expand(parsed_set, true, &options),
+            vec![
+                vec![1],
+                vec![1, 2],
+                vec![1, 2, 1],
+                vec![2],
+                vec![2, 1],
+                vec![2, 1, 2],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_repeated_items() {
+        assert!(has_repeated_items(&[1, 2, 1]));
+        assert!(!has_repeated_items(&[2, 1]));
+    }
+}
@@ -5,12 +5,17 @@ use fxhash::FxHashSet;
 use serde::{ser::SerializeSeq, Serialize};
 use std::{collections::HashSet, hash::Hash};
 
+use super::constraint::Constraints;
+use super::hasher::{IdentityHashSet, SeededAHashSet, SeededSipHashSet, WyHashSet, Xxh3HashSet};
+use super::Shrinkable;
+
 pub(crate) trait SetLike<T>
 where
     T: Eq,
     T: Hash,
 {
-    fn set_insert(&mut self, item: T);
+    /// Returns `true` if the item was not in the set before.
+    fn set_insert(&mut self, item: T) -> bool;
     fn set_contains(&self, item: &T) -> bool;
 }
 
@@ -22,8 +27,8 @@ macro_rules! impl_setlike {
             T: Hash,
         {
             #[inline]
-            fn set_insert(&mut self, item: T) {
-                self.insert(item);
+            fn set_insert(&mut self, item: T) -> bool {
+                self.insert(item)
             }
             #[inline]
             fn set_contains(&self, item: &T) -> bool {
@@ -37,6 +42,11 @@ impl_setlike!(HashSet);
 impl_setlike!(FxHashSet);
 impl_setlike!(FnvHashSet);
 impl_setlike!(AHashSet);
+impl_setlike!(Xxh3HashSet);
+impl_setlike!(WyHashSet);
+impl_setlike!(SeededSipHashSet);
+impl_setlike!(SeededAHashSet);
+impl_setlike!(IdentityHashSet);
 
 #[derive(Default)]
 pub(crate) struct WrappedAHashSet<T: Eq + Hash>(AHashSet<T>);
@@ -56,6 +66,14 @@ impl<T: Eq + Hash> std::ops::DerefMut for WrappedAHashSet<T> {
     }
 }
 
+impl<T: Eq + Hash> IntoIterator for WrappedAHashSet<T> {
+    type Item = T;
+    type IntoIter = std::collections::hash_set::IntoIter<T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
 impl<T: Eq + Hash + Serialize> Serialize for WrappedAHashSet<T> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -86,9 +104,41 @@ impl Serialize for WrappedBitVec {
     }
 }
 
+/// Clears the set bits, going on after the cleared one.
+impl Shrinkable for WrappedBitVec {
+    type Taken = ();
+
+    fn nb_positions(&self) -> usize {
+        self.0.len()
+    }
+
+    fn items(&self) -> impl Iterator<Item = u8> + '_ {
+        self.0.iter_ones().map(|i| i as u8)
+    }
+
+    fn take(&mut self, position: usize, constraints: &Constraints) -> Option<((), usize)> {
+        if !self.0[position] || constraints.is_required(position as u8) {
+            return None;
+        }
+        self.0.set(position, false);
+        Some(((), position + 1))
+    }
+
+    fn restore(&mut self, position: usize, _: ()) {
+        self.0.set(position, true);
+    }
+}
+
 #[derive(Default, PartialEq, Eq, Hash, Clone, Copy)]
 pub(crate) struct Wrappedu128(pub u128);
 
+impl Wrappedu128 {
+    #[inline]
+    pub(crate) fn items(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..u128::BITS as u8).filter(move |&i| (self.0 >> i) & 1 == 1)
+    }
+}
+
 impl Serialize for Wrappedu128 {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -104,17 +154,86 @@ impl Serialize for Wrappedu128 {
     }
 }
 
+/// Clears the set bits, going on after the cleared one.
+impl Shrinkable for Wrappedu128 {
+    type Taken = ();
+
+    fn nb_positions(&self) -> usize {
+        u128::BITS as usize
+    }
+
+    fn items(&self) -> impl Iterator<Item = u8> + '_ {
+        Wrappedu128::items(self)
+    }
+
+    fn take(&mut self, position: usize, constraints: &Constraints) -> Option<((), usize)> {
+        if (self.0 >> position) & 1 == 0 || constraints.is_required(position as u8) {
+            return None;
+        }
+        self.0 ^= 1 << position;
+        Some(((), position + 1))
+    }
+
+    fn restore(&mut self, position: usize, _: ()) {
+        self.0 ^= 1 << position;
+    }
+}
+
+/// Stored item-set as its list of items.
+pub(crate) trait ToItems {
+    /// `false` for hashes of item-sets, which cannot be turned back into items.
+    const HAS_ITEMS: bool = true;
+    fn to_items(&self) -> Vec<u8>;
+}
+
+impl ToItems for Vec<u8> {
+    fn to_items(&self) -> Vec<u8> {
+        self.clone()
+    }
+}
+
+impl ToItems for WrappedBitVec {
+    fn to_items(&self) -> Vec<u8> {
+        self.0.iter_ones().map(|i| i as u8).collect()
+    }
+}
+
+impl ToItems for Wrappedu128 {
+    fn to_items(&self) -> Vec<u8> {
+        self.items().collect()
+    }
+}
+
+macro_rules! impl_hash_to_items {
+    ($t:ty) => {
+        impl ToItems for $t {
+            const HAS_ITEMS: bool = false;
+            fn to_items(&self) -> Vec<u8> {
+                unreachable!("hashes of item-sets have no items")
+            }
+        }
+    };
+}
+
+impl_hash_to_items!(u64);
+impl_hash_to_items!(u128);
+
 pub(crate) trait SerializedSetLen: erased_serde::Serialize {
     fn set_len(&self) -> usize;
+    /// Item-sets of the set, `None` if it only holds their hashes.
+    fn itemsets(&self) -> Option<Box<dyn Iterator<Item = Vec<u8>> + '_>>;
 }
 
 macro_rules! impl_setlen {
     ($t:ident) => {
-        impl<T: Eq + Hash + Serialize> SerializedSetLen for $t<T> {
+        impl<T: Eq + Hash + Serialize + ToItems> SerializedSetLen for $t<T> {
             #[inline]
             fn set_len(&self) -> usize {
                 self.len()
             }
+            fn itemsets(&self) -> Option<Box<dyn Iterator<Item = Vec<u8>> + '_>> {
+                T::HAS_ITEMS.then(|| Box::new(self.iter().map(ToItems::to_items)) as Box<_>)
+            }
         }
     };
 }
@@ -123,6 +242,11 @@ impl_setlen!(HashSet);
 impl_setlen!(FxHashSet);
 impl_setlen!(FnvHashSet);
 impl_setlen!(WrappedAHashSet);
+impl_setlen!(Xxh3HashSet);
+impl_setlen!(WyHashSet);
+impl_setlen!(SeededSipHashSet);
+impl_setlen!(SeededAHashSet);
+impl_setlen!(IdentityHashSet);
 
 impl Serialize for dyn SerializedSetLen {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -0,0 +1,209 @@
+use serde::{ser::SerializeSeq, Serialize};
+
+use super::set::{SerializedSetLen, SetLike, ToItems, Wrappedu128};
+
+const MIN_CAPACITY: usize = 16;
+// 2^64 / golden ratio, used for fibonacci hashing of the keys into slots.
+const FIB_MULTIPLIER: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Fixed-size keys which can be stored inline in an `IntTable`.
+/// `EMPTY` marks free slots, so the key equal to it is tracked separately.
+pub(crate) trait TableKey: Copy + Eq {
+    const EMPTY: Self;
+    fn slot_hash(&self) -> u64;
+}
+
+impl TableKey for u64 {
+    const EMPTY: Self = 0;
+    #[inline]
+    fn slot_hash(&self) -> u64 {
+        *self
+    }
+}
+
+impl TableKey for u128 {
+    const EMPTY: Self = 0;
+    #[inline]
+    fn slot_hash(&self) -> u64 {
+        *self as u64
+    }
+}
+
+impl TableKey for Wrappedu128 {
+    const EMPTY: Self = Wrappedu128(0);
+    #[inline]
+    fn slot_hash(&self) -> u64 {
+        (self.0 as u64) ^ ((self.0 >> 64) as u64).rotate_left(32)
+    }
+}
+
+/// Open-addressing hash table with linear probing and power-of-two capacity
+/// for fixed-size integer keys. Avoids the generic `HashSet` overhead.
+pub(crate) struct IntTable<K> {
+    slots: Vec<K>,
+    len: usize,
+    has_empty_key: bool,
+    shift: u32,
+}
+
+impl<K: TableKey> IntTable<K> {
+    /// Pre-sizes the table so that `expected_len` keys fit without growing.
+    pub(crate) fn with_expected_len(expected_len: usize) -> Self {
+        let capacity = (expected_len.saturating_mul(4) / 3 + 1)
+            .max(MIN_CAPACITY)
+            .next_power_of_two();
+        IntTable {
+            slots: vec![K::EMPTY; capacity],
+            len: 0,
+            has_empty_key: false,
+            shift: u64::BITS - capacity.trailing_zeros(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.len + self.has_empty_key as usize
+    }
+
+    #[inline]
+    fn mask(&self) -> usize {
+        self.slots.len() - 1
+    }
+
+    #[inline]
+    fn home_slot(&self, key: &K) -> usize {
+        (key.slot_hash().wrapping_mul(FIB_MULTIPLIER) >> self.shift) as usize
+    }
+
+    /// Returns the slot holding `key` or the free slot where it would go.
+    #[inline]
+    fn find_slot(&self, key: &K) -> usize {
+        let mask = self.mask();
+        let mut idx = self.home_slot(key);
+        loop {
+            let slot = self.slots[idx];
+            if slot == *key || slot == K::EMPTY {
+                return idx;
+            }
+            idx = (idx + 1) & mask;
+        }
+    }
+
+    fn grow(&mut self) {
+        let old_slots = std::mem::take(&mut self.slots);
+        let capacity = old_slots.len() * 2;
+        self.slots = vec![K::EMPTY; capacity];
+        self.shift = u64::BITS - capacity.trailing_zeros();
+        for key in old_slots.into_iter().filter(|k| *k != K::EMPTY) {
+            let idx = self.find_slot(&key);
+            self.slots[idx] = key;
+        }
+    }
+
+    /// Returns `true` if the key was not present before.
+    #[inline]
+    pub(crate) fn insert(&mut self, key: K) -> bool {
+        if key == K::EMPTY {
+            return !std::mem::replace(&mut self.has_empty_key, true);
+        }
+        // keep the load factor under 3/4 for short probe sequences.
+        if (self.len + 1) * 4 > self.slots.len() * 3 {
+            self.grow();
+        }
+        let idx = self.find_slot(&key);
+        if self.slots[idx] == key {
+            return false;
+        }
+        self.slots[idx] = key;
+        self.len += 1;
+        true
+    }
+
+    #[inline]
+    pub(crate) fn contains(&self, key: &K) -> bool {
+        if *key == K::EMPTY {
+            return self.has_empty_key;
+        }
+        self.slots[self.find_slot(key)] == *key
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = K> + '_ {
+        let empty_key = self.has_empty_key.then_some(K::EMPTY);
+        empty_key
+            .into_iter()
+            .chain(self.slots.iter().copied().filter(|k| *k != K::EMPTY))
+    }
+}
+
+impl<K: TableKey> Default for IntTable<K> {
+    fn default() -> Self {
+        Self::with_expected_len(0)
+    }
+}
+
+impl<K> SetLike<K> for IntTable<K>
+where
+    K: TableKey + std::hash::Hash,
+{
+    #[inline]
+    fn set_insert(&mut self, item: K) -> bool {
+        self.insert(item)
+    }
+    #[inline]
+    fn set_contains(&self, item: &K) -> bool {
+        self.contains(item)
+    }
+}
+
+impl<K: TableKey + Serialize> Serialize for IntTable<K> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for e in self.iter() {
+            seq.serialize_element(&e)?;
+        }
+        seq.end()
+    }
+}
+
+impl<K: TableKey + Serialize + ToItems> SerializedSetLen for IntTable<K> {
+    #[inline]
+    fn set_len(&self) -> usize {
+        self.len()
+    }
+    fn itemsets(&self) -> Option<Box<dyn Iterator<Item = Vec<u8>> + '_>> {
+        K::HAS_ITEMS.then(|| Box::new(self.iter().map(|k| k.to_items())) as Box<_>)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_insert_contains() {
+        let mut table = IntTable::<u64>::default();
+        for i in 0..1000u64 {
+            assert!(table.insert(i * 7919));
+        }
+        assert!(!table.insert(7919));
+        assert_eq!(table.len(), 1000);
+        assert!(table.contains(&0));
+        assert!(table.contains(&(999 * 7919)));
+        assert!(!table.contains(&1));
+    }
+
+    #[test]
+    fn test_expected_len_no_grow() {
+        let mut table = IntTable::<Wrappedu128>::with_expected_len(100);
+        let capacity = table.slots.len();
+        for i in 1..=100u128 {
+            table.insert(Wrappedu128(i << 64));
+        }
+        assert_eq!(table.slots.len(), capacity);
+        assert_eq!(table.iter().count(), 100);
+    }
+}
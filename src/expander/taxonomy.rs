@@ -0,0 +1,220 @@
+use anyhow::{bail, Result};
+use fnv::FnvHashMap;
+
+use crate::expander::constraint::ItemMask;
+use crate::expander::{ExpandOptions, SetLike};
+use crate::JsonSet;
+
+/// Parent of every item in a category hierarchy (e.g. SKU → brand → category). Categories
+/// share the item ids of the items, so an item has at most one parent.
+#[derive(Debug, Clone)]
+pub(crate) struct Taxonomy {
+    parents: Vec<Option<u8>>,
+}
+
+impl Taxonomy {
+    /// Parses a JSON object mapping items to their parents, e.g. `{"3": 100, "100": 200}`.
+    pub(crate) fn from_json(contents: &str) -> Result<Self> {
+        let table: FnvHashMap<u8, u8> = serde_json::from_str(contents)?;
+        let mut parents = vec![None; u8::MAX as usize + 1];
+        for (item, parent) in table {
+            parents[item as usize] = Some(parent);
+        }
+        let taxonomy = Taxonomy { parents };
+        for item in 0..=u8::MAX {
+            // a chain of ancestors longer than the number of items goes around a cycle.
+            if taxonomy.ancestors(item).nth(u8::MAX as usize).is_some() {
+                bail!("The taxonomy has a cycle through item {}", item);
+            }
+        }
+        Ok(taxonomy)
+    }
+
+    #[inline]
+    pub(crate) fn parent(&self, item: u8) -> Option<u8> {
+        self.parents[item as usize]
+    }
+
+    /// Parent, grand-parent and so on of `item`.
+    pub(crate) fn ancestors(&self, item: u8) -> impl Iterator<Item = u8> + '_ {
+        std::iter::successors(self.parent(item), |&x| self.parent(x))
+    }
+
+    pub(crate) fn is_ancestor(&self, ancestor: u8, item: u8) -> bool {
+        self.ancestors(item).any(|x| x == ancestor)
+    }
+
+    /// Items of the item-set with all their ancestors, e.g. for the support of generalized
+    /// item-sets in a transaction.
+    pub(crate) fn with_ancestors(&self, itemset: &[u8]) -> Vec<u8> {
+        let mask = itemset
+            .iter()
+            .flat_map(|&item| std::iter::once(item).chain(self.ancestors(item)))
+            .collect::<ItemMask>();
+        mask.items()
+    }
+
+    /// Drops the items which are ancestors of other items, they are implied by them.
+    fn normalize(&self, itemset: &[u8]) -> Vec<u8> {
+        let mask = itemset
+            .iter()
+            .filter(|&&x| !itemset.iter().any(|&y| self.is_ancestor(x, y)))
+            .copied()
+            .collect::<ItemMask>();
+        mask.items()
+    }
+}
+
+/// Downward closure of the input item-sets in the generalized lattice of a taxonomy: an
+/// item-set is below another if each of its items is an item of the other or an ancestor
+/// of one. Item-sets with an item and one of its ancestors are redundant and never
+/// generated.
+pub(crate) struct GeneralizedExpander<T> {
+    _phantom: std::marker::PhantomData<T>,
+}
+
+impl<T> GeneralizedExpander<T>
+where
+    T: Default,
+    T: SetLike<Vec<u8>>,
+{
+    /// Hands every distinct generalized item-set to `sink` as soon as it is found and
+    /// returns their number. Each step removes an item or replaces it by its parent, which
+    /// reaches every item-set below the input without going through redundant ones. The
+    /// search does not go below `min_size`; an item may get generalized into a required
+    /// item, so `max_size` and the constraints only filter the output.
+    pub(crate) fn expand_streaming(
+        parsed_set: Vec<JsonSet>,
+        taxonomy: &Taxonomy,
+        options: &ExpandOptions,
+        sink: &mut impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<usize> {
+        let mut visited = T::default();
+        let mut len = 0;
+        for i in parsed_set {
+            let solution = taxonomy.normalize(&i.set);
+            Self::expand_one_solution(solution, taxonomy, &mut visited, options, sink, &mut len)?;
+        }
+        Ok(len)
+    }
+
+    fn expand_one_solution(
+        solution: Vec<u8>,
+        taxonomy: &Taxonomy,
+        visited: &mut T,
+        options: &ExpandOptions,
+        sink: &mut impl FnMut(&[u8]) -> Result<()>,
+        len: &mut usize,
+    ) -> Result<()> {
+        if solution.len() < options.min_size || !visited.set_insert(solution.clone()) {
+            return Ok(());
+        }
+        let mask = solution.iter().copied().collect::<ItemMask>();
+        if solution.len() <= options.max_size
+            && options.constraints.required.is_subset(&mask)
+            && options.constraints.admits(&mask)
+        {
+            sink(&solution)?;
+            *len += 1;
+        }
+        for i in 0..solution.len() {
+            let item = solution[i];
+            if solution.len() > options.min_size {
+                let mut subset = solution.clone();
+                subset.remove(i);
+                Self::expand_one_solution(subset, taxonomy, visited, options, sink, len)?;
+            }
+            let Some(parent) = taxonomy.parent(item) else {
+                continue;
+            };
+            // the parent would be redundant next to another of its descendants.
+            if solution
+                .iter()
+                .any(|&x| x != item && taxonomy.is_ancestor(parent, x))
+            {
+                continue;
+            }
+            let mut generalized = solution.clone();
+            generalized[i] = parent;
+            generalized.sort_unstable();
+            Self::expand_one_solution(generalized, taxonomy, visited, options, sink, len)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use fnv::FnvHashSet;
+
+    use crate::expander::constraint::Constraints;
+
+    use super::*;
+
+    // 1, 2 -> 10 -> 20 and 3 -> 11 -> 20.
+    fn taxonomy() -> Taxonomy {
+        Taxonomy::from_json(r#"{"1": 10, "2": 10, "3": 11, "10": 20, "11": 20}"#).unwrap()
+    }
+
+    fn expand(parsed_set: Vec<JsonSet>, options: &ExpandOptions) -> Vec<Vec<u8>> {
+        let mut itemsets = Vec::new();
+        let len = GeneralizedExpander::<FnvHashSet<Vec<u8>>>::expand_streaming(
+            parsed_set,
+            &taxonomy(),
+            options,
+            &mut |x| {
+                itemsets.push(x.to_vec());
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(len, itemsets.len());
+        itemsets.sort();
+        itemsets
+    }
+
+    #[test]
+    fn test_taxonomy() {
+        let taxonomy = taxonomy();
+        assert!(taxonomy.is_ancestor(20, 1));
+        assert!(!taxonomy.is_ancestor(11, 1));
+        assert_eq!(taxonomy.with_ancestors(&[1, 3]), vec![1, 3, 10, 11, 20]);
+        assert_eq!(taxonomy.normalize(&[20, 1, 2, 10]), vec![1, 2]);
+        assert!(Taxonomy::from_json(r#"{"1": 2, "2": 3, "3": 1}"#).is_err());
+    }
+
+    #[test]
+    fn test_1_generalized() {
+        let itemsets = expand(vec![JsonSet { set: vec![1, 3] }], &ExpandOptions::default());
+        assert_eq!(
+            itemsets,
+            vec![
+                vec![1],
+                vec![1, 3],
+                vec![1, 11],
+                vec![3],
+                vec![3, 10],
+                vec![10],
+                vec![10, 11],
+                vec![11],
+                vec![20],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_1_generalized_constraints() {
+        let options = ExpandOptions {
+            min_size: 2,
+            constraints: Constraints {
+                required: [10].into_iter().collect(),
+                ..Constraints::default()
+            },
+            ..ExpandOptions::default()
+        };
+        let itemsets = expand(vec![JsonSet { set: vec![1, 2, 3] }], &options);
+        // {1, 2} generalizes to {10} only, below the minimum size.
+        assert_eq!(itemsets, vec![vec![3, 10], vec![10, 11]]);
+    }
+}
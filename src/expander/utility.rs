@@ -0,0 +1,123 @@
+use anyhow::{bail, Result};
+use fnv::FnvHashMap;
+use serde::Serialize;
+
+/// Weight (e.g. price or margin) of every item, 0 for the items missing from the table.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ItemWeights(Vec<f64>);
+
+impl ItemWeights {
+    /// Parses a JSON object mapping items to their weights, e.g. `{"1": 2.5, "7": 10}`.
+    pub(crate) fn from_json(contents: &str) -> Result<Self> {
+        let table: FnvHashMap<u8, f64> = serde_json::from_str(contents)?;
+        let mut weights = vec![0.0; u8::MAX as usize + 1];
+        for (item, weight) in table {
+            if !weight.is_finite() {
+                bail!("The weight of item {} is not a finite number", item);
+            }
+            weights[item as usize] = weight;
+        }
+        Ok(ItemWeights(weights))
+    }
+
+    pub(crate) fn has_negative(&self) -> bool {
+        self.0.iter().any(|&w| w < 0.0)
+    }
+
+    #[inline]
+    pub(crate) fn sum(&self, items: impl IntoIterator<Item = u8>) -> f64 {
+        items.into_iter().map(|item| self.0[item as usize]).sum()
+    }
+
+    /// Weights of the items renumbered by their position in `original`.
+    pub(crate) fn remapped(&self, original: &[u8]) -> Self {
+        let mut weights = vec![0.0; u8::MAX as usize + 1];
+        for (id, &item) in original.iter().enumerate() {
+            weights[id] = self.0[item as usize];
+        }
+        ItemWeights(weights)
+    }
+
+    pub(crate) fn utility(&self, itemset: &[u8]) -> Utility {
+        let weights = itemset.iter().map(|&item| self.0[item as usize]);
+        Utility {
+            sum: self.sum(itemset.iter().copied()),
+            min: weights.clone().reduce(f64::min),
+            max: weights.reduce(f64::max),
+        }
+    }
+}
+
+/// Aggregated weights of an output item-set, `min` and `max` are null for the empty one.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub(crate) struct Utility {
+    pub(crate) sum: f64,
+    pub(crate) min: Option<f64>,
+    pub(crate) max: Option<f64>,
+}
+
+/// Lower bound on the summed weight of the expanded item-sets. With non-negative weights a
+/// subset never weighs more than its superset, so the expansion stops at the first item-set
+/// below the bound.
+#[derive(Debug, Clone)]
+pub(crate) struct MinUtility {
+    pub(crate) weights: ItemWeights,
+    pub(crate) min_utility: f64,
+}
+
+impl MinUtility {
+    pub(crate) fn new(weights: ItemWeights, min_utility: f64) -> Result<Self> {
+        if weights.has_negative() {
+            bail!("Pruning with a minimum utility requires non-negative item weights");
+        }
+        Ok(MinUtility {
+            weights,
+            min_utility,
+        })
+    }
+
+    #[inline]
+    pub(crate) fn admits(&self, items: impl IntoIterator<Item = u8>) -> bool {
+        self.weights.sum(items) >= self.min_utility
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn weights() -> ItemWeights {
+        ItemWeights::from_json(r#"{"1": 2.5, "2": 1, "7": 10}"#).unwrap()
+    }
+
+    #[test]
+    fn test_utility() {
+        assert_eq!(
+            weights().utility(&[1, 3, 7]),
+            Utility {
+                sum: 12.5,
+                min: Some(0.0),
+                max: Some(10.0),
+            }
+        );
+        assert_eq!(
+            weights().utility(&[]),
+            Utility {
+                sum: 0.0,
+                min: None,
+                max: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_min_utility() {
+        let min_utility = MinUtility::new(weights(), 3.0).unwrap();
+        assert!(min_utility.admits([1, 2]));
+        assert!(!min_utility.admits([1, 3]));
+        let negative = ItemWeights::from_json(r#"{"1": -1}"#).unwrap();
+        assert!(MinUtility::new(negative, 0.0).is_err());
+        assert!(ItemWeights::from_json(r#"{"300": 1}"#).is_err());
+    }
+}
@@ -1,4 +1,6 @@
-use crate::expander::Expander;
+use std::convert::Infallible;
+
+use crate::expander::{descend_to_max_size, ExpandOptions, Expander};
 use crate::JsonSet;
 
 pub(crate) struct VecExpander<T> {
@@ -14,26 +16,46 @@ where
     type SetType = T;
     type HashType = Vec<u8>;
 
-    fn expand(parsed_set: Vec<JsonSet>) -> T {
-        let mut final_set = T::default();
-        for mut i in parsed_set {
-            Self::expand_one_solution_to_lower_level(&mut i.set, &mut final_set);
+    fn expand_into(parsed_set: Vec<JsonSet>, mut final_set: T, options: &ExpandOptions) -> T {
+        for mut i in options.constraints.constrain_input(parsed_set) {
+            Self::expand_one_solution_to_lower_level(&mut i.set, &mut final_set, options);
         }
         final_set
     }
 
-    fn expand_one_solution_to_lower_level(solution: &mut Self::SolutionType, final_set: &mut T) {
+    fn expand_one_solution_to_lower_level(
+        solution: &mut Self::SolutionType,
+        final_set: &mut T,
+        options: &ExpandOptions,
+    ) {
+        if options.is_below_min_utility(solution.iter().copied()) {
+            return;
+        }
         let length = solution.len();
-        if length > 1 {
+        if length > options.max_size {
+            let Ok(()) = descend_to_max_size(solution, length, 0, options, &mut |x| {
+                if !final_set.set_contains(x) {
+                    Self::expand_one_solution_to_lower_level(x, final_set, options);
+                }
+                Ok::<(), Infallible>(())
+            });
+            return;
+        }
+        if length > options.min_size {
             for i in 0..length {
+                if options.constraints.is_required(solution[i]) {
+                    continue;
+                }
                 let el = solution.remove(i);
                 if !final_set.set_contains(solution) {
-                    Self::expand_one_solution_to_lower_level(solution, final_set);
+                    Self::expand_one_solution_to_lower_level(solution, final_set, options);
                 }
                 solution.insert(i, el);
             }
         }
-        final_set.set_insert(solution.clone());
+        if length >= options.min_size {
+            final_set.set_insert(solution.clone());
+        }
     }
 }
 
@@ -47,6 +69,8 @@ mod tests {
 
     use crate::expander::set::WrappedAHashSet;
 
+    use crate::expander::utility::{ItemWeights, MinUtility};
+
     use super::*;
     #[test]
     fn test_1_fnv() {
@@ -171,4 +195,38 @@ mod tests {
         let serialized_set = serde_json::to_string(&expanded_set).unwrap();
         assert_eq!(serialized_set.len(), 140);
     }
+
+    #[test]
+    fn test_2_empty_set() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![57, 58, 59, 60],
+            },
+            JsonSet { set: vec![60, 99] },
+        ];
+        let options = ExpandOptions {
+            min_size: 0,
+            ..ExpandOptions::default()
+        };
+        let expanded_set = VecExpander::<FnvHashSet<Vec<u8>>>::expand_with(parsed_set, &options);
+        assert_eq!(expanded_set.len(), 18);
+        assert!(expanded_set.contains(&vec![]));
+    }
+
+    #[test]
+    fn test_1_min_utility() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![1, 2, 3, 4],
+            },
+            JsonSet { set: vec![4, 5, 6] },
+        ];
+        let weights = ItemWeights::from_json(r#"{"1": 1, "2": 2, "3": 3, "4": 4, "5": 5, "6": 6}"#);
+        let options = ExpandOptions {
+            min_utility: Some(MinUtility::new(weights.unwrap(), 7.0).unwrap()),
+            ..ExpandOptions::default()
+        };
+        let expanded_set = VecExpander::<FnvHashSet<Vec<u8>>>::expand_with(parsed_set, &options);
+        assert_eq!(expanded_set.len(), 9);
+    }
 }
@@ -1,47 +1,123 @@
-use crate::expander::Expander;
+use crate::expander::{descend_to_max_size, ExpandOptions, Expander};
 use crate::JsonSet;
+use std::convert::Infallible;
 use std::hash::{Hash, Hasher};
 
-pub(crate) struct VecHashOnlyExpander<T, S> {
+// Prefix for the second, independent 64-bit half of a wide hash.
+const WIDE_HASH_SALT: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Hash of an itemset which is stored instead of the itemset itself.
+pub(crate) trait Fingerprint: Copy + Default + Eq + Hash {
+    fn of<S: Hasher + Default>(solution: &[u8]) -> Self;
+}
+
+impl Fingerprint for u64 {
+    #[inline]
+    fn of<S: Hasher + Default>(solution: &[u8]) -> Self {
+        let mut hasher = S::default();
+        Hash::hash_slice(solution, &mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Two independent 64-bit hashes (the second one salted) so that collisions are negligible.
+impl Fingerprint for u128 {
+    #[inline]
+    fn of<S: Hasher + Default>(solution: &[u8]) -> Self {
+        let mut salted_hasher = S::default();
+        salted_hasher.write_u64(WIDE_HASH_SALT);
+        Hash::hash_slice(solution, &mut salted_hasher);
+        ((salted_hasher.finish() as u128) << 64) | u64::of::<S>(solution) as u128
+    }
+}
+
+pub(crate) struct VecHashOnlyExpander<T, S, F = u64> {
     _phantom_hash_set: std::marker::PhantomData<T>,
     _phantom_hasher: std::marker::PhantomData<S>,
+    _phantom_fingerprint: std::marker::PhantomData<F>,
 }
 
-impl<T, S> Expander for VecHashOnlyExpander<T, S>
+impl<T, S, F> Expander for VecHashOnlyExpander<T, S, F>
 where
     T: Default,
-    T: crate::expander::SetLike<u64>,
+    T: crate::expander::SetLike<F>,
     S: Hasher,
     S: Default,
+    F: Fingerprint,
 {
     type SolutionType = Vec<u8>;
     type SetType = T;
-    type HashType = u64;
+    type HashType = F;
+
+    fn expand_into(parsed_set: Vec<JsonSet>, final_set: T, options: &ExpandOptions) -> T {
+        Self::expand_streaming(parsed_set, final_set, options, &mut |_| {})
+    }
 
-    fn expand(parsed_set: Vec<JsonSet>) -> T {
-        let mut final_set = T::default();
-        for mut i in parsed_set {
-            Self::expand_one_solution_to_lower_level(&mut i.set, &mut final_set);
+    fn expand_one_solution_to_lower_level(
+        solution: &mut Self::SolutionType,
+        final_set: &mut T,
+        options: &ExpandOptions,
+    ) {
+        Self::expand_one_solution_with_sink(solution, final_set, options, &mut |_| {});
+    }
+}
+
+impl<T, S, F> VecHashOnlyExpander<T, S, F>
+where
+    T: Default,
+    T: crate::expander::SetLike<F>,
+    S: Hasher,
+    S: Default,
+    F: Fingerprint,
+{
+    /// Expands like `expand_into` but also hands every item-set to `sink` the moment
+    /// its hash is first inserted, so the output holds the actual item-sets.
+    pub(crate) fn expand_streaming(
+        parsed_set: Vec<JsonSet>,
+        mut final_set: T,
+        options: &ExpandOptions,
+        sink: &mut impl FnMut(&[u8]),
+    ) -> T {
+        for mut i in options.constraints.constrain_input(parsed_set) {
+            Self::expand_one_solution_with_sink(&mut i.set, &mut final_set, options, sink);
         }
         final_set
     }
 
-    fn expand_one_solution_to_lower_level(solution: &mut Self::SolutionType, final_set: &mut T) {
+    fn expand_one_solution_with_sink(
+        solution: &mut Vec<u8>,
+        final_set: &mut T,
+        options: &ExpandOptions,
+        sink: &mut impl FnMut(&[u8]),
+    ) {
+        if options.is_below_min_utility(solution.iter().copied()) {
+            return;
+        }
         let length = solution.len();
-        if length > 1 {
+        if length > options.max_size {
+            let Ok(()) = descend_to_max_size(solution, length, 0, options, &mut |x| {
+                if !final_set.set_contains(&F::of::<S>(x)) {
+                    Self::expand_one_solution_with_sink(x, final_set, options, sink);
+                }
+                Ok::<(), Infallible>(())
+            });
+            return;
+        }
+        if length > options.min_size {
             for i in 0..length {
+                if options.constraints.is_required(solution[i]) {
+                    continue;
+                }
                 let el = solution.remove(i);
-                let mut hasher = S::default();
-                Hash::hash_slice(solution, &mut hasher);
-                if !final_set.set_contains(&hasher.finish()) {
-                    Self::expand_one_solution_to_lower_level(solution, final_set);
+                if !final_set.set_contains(&F::of::<S>(solution)) {
+                    Self::expand_one_solution_with_sink(solution, final_set, options, sink);
                 }
                 solution.insert(i, el);
             }
         }
-        let mut hasher = S::default();
-        Hash::hash_slice(solution, &mut hasher);
-        final_set.set_insert(hasher.finish());
+        if length >= options.min_size && final_set.set_insert(F::of::<S>(solution)) {
+            sink(solution);
+        }
     }
 }
 
@@ -54,7 +130,9 @@ mod tests {
     use fnv::{FnvHashSet, FnvHasher};
     use fxhash::{FxHashSet, FxHasher};
 
+    use crate::expander::hasher::IdentityHashSet;
     use crate::expander::set::WrappedAHashSet;
+    use crate::expander::table::IntTable;
 
     use super::*;
     #[test]
@@ -157,4 +235,103 @@ mod tests {
             17
         );
     }
+
+    #[test]
+    fn test_1_table() {
+        let parsed_set = vec![
+            JsonSet { set: vec![1, 2, 3] },
+            JsonSet { set: vec![4, 5, 6] },
+        ];
+        assert_eq!(
+            VecHashOnlyExpander::<IntTable<u64>, FnvHasher>::expand(parsed_set).len(),
+            14
+        );
+    }
+    #[test]
+    fn test_2_table() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![57, 58, 59, 60],
+            },
+            JsonSet { set: vec![60, 99] },
+        ];
+        assert_eq!(
+            VecHashOnlyExpander::<IntTable<u64>, FnvHasher>::expand(parsed_set).len(),
+            17
+        );
+    }
+
+    #[test]
+    fn test_1_pass_through() {
+        let parsed_set = vec![
+            JsonSet { set: vec![1, 2, 3] },
+            JsonSet { set: vec![4, 5, 6] },
+        ];
+        assert_eq!(
+            VecHashOnlyExpander::<IdentityHashSet<u64>, xxhash_rust::xxh3::Xxh3>::expand(
+                parsed_set
+            )
+            .len(),
+            14
+        );
+    }
+    #[test]
+    fn test_2_pass_through() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![57, 58, 59, 60],
+            },
+            JsonSet { set: vec![60, 99] },
+        ];
+        assert_eq!(
+            VecHashOnlyExpander::<IdentityHashSet<u64>, xxhash_rust::xxh3::Xxh3>::expand(
+                parsed_set
+            )
+            .len(),
+            17
+        );
+    }
+
+    #[test]
+    fn test_1_wide() {
+        let parsed_set = vec![
+            JsonSet { set: vec![1, 2, 3] },
+            JsonSet { set: vec![4, 5, 6] },
+        ];
+        assert_eq!(
+            VecHashOnlyExpander::<FxHashSet<u128>, FxHasher, u128>::expand(parsed_set).len(),
+            14
+        );
+    }
+    #[test]
+    fn test_2_wide() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![57, 58, 59, 60],
+            },
+            JsonSet { set: vec![60, 99] },
+        ];
+        assert_eq!(
+            VecHashOnlyExpander::<FxHashSet<u128>, FxHasher, u128>::expand(parsed_set).len(),
+            17
+        );
+    }
+
+    #[test]
+    fn test_1_streaming() {
+        let parsed_set = vec![
+            JsonSet { set: vec![1, 2, 3] },
+            JsonSet { set: vec![4, 5, 6] },
+        ];
+        let mut streamed = Vec::new();
+        let hashes = VecHashOnlyExpander::<FnvHashSet<u64>, FnvHasher>::expand_streaming(
+            parsed_set,
+            FnvHashSet::default(),
+            &ExpandOptions::default(),
+            &mut |itemset| streamed.push(itemset.to_vec()),
+        );
+        assert_eq!(hashes.len(), 14);
+        assert_eq!(streamed.len(), 14);
+        assert_eq!(streamed.iter().collect::<HashSet<_>>().len(), 14);
+    }
 }
@@ -0,0 +1,410 @@
+use anyhow::{bail, Context, Result};
+use fnv::FnvHashSet;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::expander::prune::SupersetIndex;
+use crate::expander::{descend_to_max_size, ExpandOptions};
+use crate::JsonSet;
+
+// Vec header and control byte per bucket of the buffer's hash table.
+const BUCKET_BYTES: usize = std::mem::size_of::<Vec<u8>>() + 1;
+
+// Runs merged at once, each holding an open file and its read buffer.
+const MERGE_FAN_IN: usize = 256;
+
+/// Parses sizes like `4096`, `512K`, `256M` or `2G` (binary units) into bytes.
+pub(crate) fn parse_memory_size(s: &str) -> Result<usize, String> {
+    let s = s.trim();
+    let (digits, shift) = match s.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&s[..s.len() - 1], 10),
+        Some('M') => (&s[..s.len() - 1], 20),
+        Some('G') => (&s[..s.len() - 1], 30),
+        _ => (s, 0),
+    };
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+        .ok_or_else(|| format!("invalid memory size '{}'", s))
+}
+
+/// Bytes of a hash table able to hold `capacity` item-sets, at most 7/8 of its buckets
+/// being full.
+fn table_bytes(capacity: usize) -> usize {
+    capacity.div_ceil(7) * 8 * BUCKET_BYTES
+}
+
+/// Heap bytes taken by the items of an item-set of `len` items: allocators hand out chunks
+/// of 16 bytes with an 8-byte header, 32 bytes at least (glibc's malloc).
+fn allocation_bytes(len: usize) -> usize {
+    match len {
+        0 => 0,
+        _ => (len + 8).max(32).next_multiple_of(16),
+    }
+}
+
+static NB_RUN_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// Temporary directory for the sorted runs, removed on drop.
+struct RunDir(PathBuf);
+
+impl RunDir {
+    fn create() -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "expander-rs-{}-{}",
+            std::process::id(),
+            NB_RUN_DIRS.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path)
+            .with_context(|| format!("cannot create run directory {}", path.display()))?;
+        Ok(RunDir(path))
+    }
+}
+
+impl Drop for RunDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Reads back item-sets written by `ExternalExpander::spill` in sorted order.
+struct RunReader(BufReader<File>);
+
+impl RunReader {
+    fn next_itemset(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut len = [0; 2];
+        match self.0.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let mut itemset = vec![0; u16::from_le_bytes(len) as usize];
+        self.0.read_exact(&mut itemset)?;
+        Ok(Some(itemset))
+    }
+}
+
+/// Writes item-sets to a run in the format read by `RunReader`.
+struct RunWriter(BufWriter<File>);
+
+impl RunWriter {
+    fn create(path: &Path) -> Result<Self> {
+        Ok(RunWriter(BufWriter::new(File::create(path)?)))
+    }
+
+    fn write_itemset(&mut self, itemset: &[u8]) -> Result<()> {
+        self.0.write_all(&(itemset.len() as u16).to_le_bytes())?;
+        self.0.write_all(itemset)?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        self.0.flush()?;
+        Ok(())
+    }
+}
+
+/// k-way merge of sorted runs, skipping the duplicates across runs. Returns the number of
+/// distinct item-sets.
+fn merge_runs(runs: &[PathBuf], sink: &mut impl FnMut(&[u8]) -> Result<()>) -> Result<usize> {
+    let mut readers = Vec::with_capacity(runs.len());
+    let mut heap = BinaryHeap::with_capacity(runs.len());
+    for (idx, path) in runs.iter().enumerate() {
+        let mut reader = RunReader(BufReader::new(File::open(path)?));
+        match reader.next_itemset()? {
+            Some(itemset) => heap.push(Reverse((itemset, idx))),
+            None => bail!("empty run {}", path.display()),
+        }
+        readers.push(reader);
+    }
+    let mut len = 0;
+    let mut last: Option<Vec<u8>> = None;
+    while let Some(Reverse((itemset, idx))) = heap.pop() {
+        if let Some(next) = readers[idx].next_itemset()? {
+            heap.push(Reverse((next, idx)));
+        }
+        if last.as_ref() != Some(&itemset) {
+            sink(&itemset)?;
+            len += 1;
+            last = Some(itemset);
+        }
+    }
+    Ok(len)
+}
+
+/// Out-of-core Vec expansion. Item-sets are kept in memory until the buffer reaches
+/// the memory limit, then written to disk as a sorted run. Runs are deduplicated
+/// with a k-way merge at the end, so inputs which fit in memory never touch the disk.
+pub(crate) struct ExternalExpander {
+    memory_limit: usize,
+    options: ExpandOptions,
+    /// Runs merged at once, more are first merged into intermediate runs.
+    merge_fan_in: usize,
+    run_dir: Option<RunDir>,
+    /// Runs left to merge.
+    runs: Vec<PathBuf>,
+    nb_runs: usize,
+    nb_run_files: usize,
+    buffer: FnvHashSet<Vec<u8>>,
+    /// Inputs walked so far. Spilling empties the buffer the walk is pruned with, but an
+    /// item-set contained in a walked input has all its subsets spilled or buffered already.
+    walked: SupersetIndex,
+    /// Heap bytes of the buffered item-sets, on top of the buffer's hash table.
+    heap_bytes: usize,
+}
+
+impl ExternalExpander {
+    pub(crate) fn new(memory_limit: usize, options: ExpandOptions) -> Self {
+        ExternalExpander {
+            memory_limit,
+            options,
+            merge_fan_in: MERGE_FAN_IN,
+            run_dir: None,
+            runs: Vec::new(),
+            nb_runs: 0,
+            nb_run_files: 0,
+            buffer: FnvHashSet::default(),
+            walked: SupersetIndex::default(),
+            heap_bytes: 0,
+        }
+    }
+
+    /// Number of sorted runs spilled to disk so far, intermediate merges excluded.
+    pub(crate) fn nb_runs(&self) -> usize {
+        self.nb_runs
+    }
+
+    /// Expands `parsed_set` and hands every distinct item-set to `sink` in sorted order.
+    /// Returns the number of item-sets.
+    pub(crate) fn expand(
+        &mut self,
+        parsed_set: Vec<JsonSet>,
+        sink: &mut impl FnMut(&[u8]) -> Result<()>,
+    ) -> Result<usize> {
+        for mut i in self.options.constraints.constrain_input(parsed_set) {
+            if !self.is_expanded(&i.set) {
+                self.expand_one_solution_to_lower_level(&mut i.set)?;
+                self.walked.insert(&i.set);
+            }
+        }
+        if self.runs.is_empty() {
+            let mut itemsets = self.buffer.drain().collect::<Vec<_>>();
+            itemsets.sort_unstable();
+            for itemset in &itemsets {
+                sink(itemset)?;
+            }
+            return Ok(itemsets.len());
+        }
+        self.spill()?;
+        self.merge(sink)
+    }
+
+    fn expand_one_solution_to_lower_level(&mut self, solution: &mut Vec<u8>) -> Result<()> {
+        if self.options.is_below_min_utility(solution.iter().copied()) {
+            return Ok(());
+        }
+        let length = solution.len();
+        if length > self.options.max_size {
+            // the walk reads the options while the visit expands into the buffer.
+            let options = self.options.clone();
+            return descend_to_max_size(solution, length, 0, &options, &mut |x| {
+                if !self.is_expanded(x) {
+                    self.expand_one_solution_to_lower_level(x)?;
+                }
+                Ok(())
+            });
+        }
+        if length > self.options.min_size {
+            for i in 0..length {
+                if self.options.constraints.is_required(solution[i]) {
+                    continue;
+                }
+                let el = solution.remove(i);
+                if !self.is_expanded(solution) {
+                    self.expand_one_solution_to_lower_level(solution)?;
+                }
+                solution.insert(i, el);
+            }
+        }
+        if length >= self.options.min_size && !self.buffer.contains(solution) {
+            self.reserve(length)?;
+            self.buffer.insert(solution.clone());
+            self.heap_bytes += allocation_bytes(length);
+        }
+        Ok(())
+    }
+
+    /// Whether the item-set and its subsets were already output, to the buffer or to a run.
+    /// The walked inputs are only needed once something was spilled.
+    fn is_expanded(&self, itemset: &[u8]) -> bool {
+        self.buffer.contains(itemset) || (self.nb_runs > 0 && self.walked.has_superset(itemset))
+    }
+
+    /// Spills the buffer if one more item-set of `len` items would take it past the memory
+    /// limit, counting its hash table as already grown if it is full.
+    fn reserve(&mut self, len: usize) -> Result<()> {
+        let capacity = match self.buffer.len() < self.buffer.capacity() {
+            true => self.buffer.capacity(),
+            false => (self.buffer.capacity() + 1) * 2,
+        };
+        if table_bytes(capacity) + self.heap_bytes + allocation_bytes(len) > self.memory_limit {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffer as a sorted run and clears it.
+    fn spill(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let path = self.next_run_path()?;
+        let mut itemsets = self.buffer.drain().collect::<Vec<_>>();
+        itemsets.sort_unstable();
+        let mut writer = RunWriter::create(&path)?;
+        for itemset in &itemsets {
+            writer.write_itemset(itemset)?;
+        }
+        writer.finish()?;
+        self.runs.push(path);
+        self.heap_bytes = 0;
+        self.nb_runs += 1;
+        Ok(())
+    }
+
+    fn next_run_path(&mut self) -> Result<PathBuf> {
+        if self.run_dir.is_none() {
+            self.run_dir = Some(RunDir::create()?);
+        }
+        self.nb_run_files += 1;
+        let run_dir = &self.run_dir.as_ref().unwrap().0;
+        Ok(run_dir.join(format!("run-{}", self.nb_run_files)))
+    }
+
+    /// Merges the runs, at most `merge_fan_in` at once so that the open files stay bounded:
+    /// the oldest ones are first merged into intermediate runs until few enough are left.
+    fn merge(&mut self, sink: &mut impl FnMut(&[u8]) -> Result<()>) -> Result<usize> {
+        while self.runs.len() > self.merge_fan_in {
+            let batch = self.runs.drain(..self.merge_fan_in).collect::<Vec<_>>();
+            let path = self.next_run_path()?;
+            let mut writer = RunWriter::create(&path)?;
+            merge_runs(&batch, &mut |itemset| writer.write_itemset(itemset))?;
+            writer.finish()?;
+            for run in &batch {
+                std::fs::remove_file(run)?;
+            }
+            self.runs.push(path);
+        }
+        merge_runs(&self.runs, sink)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn expand_all(parsed_set: Vec<JsonSet>, memory_limit: usize) -> (Vec<Vec<u8>>, usize) {
+        let mut expander = ExternalExpander::new(memory_limit, ExpandOptions::default());
+        let mut itemsets = Vec::new();
+        expander
+            .expand(parsed_set, &mut |itemset| {
+                itemsets.push(itemset.to_vec());
+                Ok(())
+            })
+            .unwrap();
+        (itemsets, expander.nb_runs())
+    }
+
+    #[test]
+    fn test_1_in_memory() {
+        let parsed_set = vec![
+            JsonSet { set: vec![1, 2, 3] },
+            JsonSet { set: vec![4, 5, 6] },
+        ];
+        let (itemsets, nb_runs) = expand_all(parsed_set, usize::MAX);
+        assert_eq!(itemsets.len(), 14);
+        assert_eq!(nb_runs, 0);
+    }
+
+    #[test]
+    fn test_2_spilled() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![57, 58, 59, 60],
+            },
+            JsonSet { set: vec![60, 99] },
+            JsonSet {
+                set: vec![57, 58, 60],
+            },
+        ];
+        let (itemsets, nb_runs) = expand_all(parsed_set, 512);
+        assert!(nb_runs > 1);
+        assert_eq!(itemsets.len(), 17);
+        assert!(itemsets.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_3_merge_fan_in() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![1, 2, 3, 4, 5],
+            },
+            JsonSet {
+                set: vec![4, 5, 6, 7],
+            },
+        ];
+        let mut expander = ExternalExpander::new(0, ExpandOptions::default());
+        expander.merge_fan_in = 3;
+        let mut itemsets = Vec::new();
+        expander
+            .expand(parsed_set, &mut |itemset| {
+                itemsets.push(itemset.to_vec());
+                Ok(())
+            })
+            .unwrap();
+        assert!(expander.nb_runs() > 3 * 3);
+        assert_eq!(itemsets.len(), 31 + 15 - 3);
+        assert!(itemsets.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_4_walked_inputs() {
+        let input = JsonSet {
+            set: vec![1, 2, 3, 4, 5],
+        };
+        let (_, nb_runs) = expand_all(vec![input.clone()], 0);
+        let parsed_set = vec![
+            input,
+            JsonSet { set: vec![1, 2, 3] },
+            JsonSet { set: vec![2, 3, 4] },
+        ];
+        // the inputs contained in the first one are not walked again after the spills.
+        let (itemsets, nb_runs_covered) = expand_all(parsed_set, 0);
+        assert_eq!(nb_runs_covered, nb_runs);
+        assert_eq!(itemsets.len(), 31);
+    }
+
+    #[test]
+    fn test_buffer_bytes() {
+        assert_eq!(allocation_bytes(0), 0);
+        assert_eq!(allocation_bytes(3), 32);
+        assert_eq!(allocation_bytes(30), 48);
+        assert_eq!(table_bytes(7), 8 * BUCKET_BYTES);
+        assert_eq!(table_bytes(14), 16 * BUCKET_BYTES);
+    }
+
+    #[test]
+    fn test_parse_memory_size() {
+        assert_eq!(parse_memory_size("4096"), Ok(4096));
+        assert_eq!(parse_memory_size("2k"), Ok(2048));
+        assert_eq!(parse_memory_size("3M"), Ok(3 << 20));
+        assert_eq!(parse_memory_size("1G"), Ok(1 << 30));
+        assert!(parse_memory_size("lots").is_err());
+    }
+}
@@ -0,0 +1,277 @@
+use std::str::FromStr;
+
+use crate::items::{parse_item_list, ItemMask};
+
+/// Predicate on output item-sets, e.g. `size >= 3 && contains(5) && !contains_any(10,11)`.
+///
+/// - `size` and `support` (number of input item-sets or transactions containing the
+///   item-set) are compared with `==`, `!=`, `<`, `<=`, `>` or `>=` to an integer,
+/// - `contains(..)` holds if the item-set contains all of the listed items,
+///   `contains_any(..)` if it contains at least one, lists use the `--require` syntax,
+/// - `!`, `&&`, `||`, parentheses, `true` and `false` as usual.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterExpr {
+    Const(bool),
+    Compare(Metric, CompareOp, usize),
+    Contains(ItemMask),
+    ContainsAny(ItemMask),
+    Not(Box<FilterExpr>),
+    And(Box<FilterExpr>, Box<FilterExpr>),
+    Or(Box<FilterExpr>, Box<FilterExpr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    Size,
+    Support,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn holds(self, lhs: usize, rhs: usize) -> bool {
+        match self {
+            CompareOp::Eq => lhs == rhs,
+            CompareOp::Ne => lhs != rhs,
+            CompareOp::Lt => lhs < rhs,
+            CompareOp::Le => lhs <= rhs,
+            CompareOp::Gt => lhs > rhs,
+            CompareOp::Ge => lhs >= rhs,
+        }
+    }
+}
+
+impl FilterExpr {
+    /// Whether the expression reads `support`, which needs the input item-sets.
+    pub fn uses_support(&self) -> bool {
+        match self {
+            FilterExpr::Compare(metric, _, _) => *metric == Metric::Support,
+            FilterExpr::Not(e) => e.uses_support(),
+            FilterExpr::And(l, r) | FilterExpr::Or(l, r) => l.uses_support() || r.uses_support(),
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for FilterExpr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { input: s, pos: 0 };
+        let expr = parser.or()?;
+        parser.skip_whitespace();
+        match parser.pos == s.len() {
+            true => Ok(expr),
+            false => Err(parser.error("unexpected input")),
+        }
+    }
+}
+
+/// Recursive descent over the filter string, `||` binding looser than `&&`.
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: &str) -> String {
+        format!(
+            "invalid filter '{}': {} at position {}",
+            self.input, msg, self.pos
+        )
+    }
+
+    fn rest(&self) -> &str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.pos = self.input.len() - self.rest().trim_start().len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        let found = self.rest().starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &str {
+        self.skip_whitespace();
+        let start = self.pos;
+        let len = self.rest().find(|c| !f(c)).unwrap_or(self.rest().len());
+        self.pos += len;
+        &self.input[start..self.pos]
+    }
+
+    fn or(&mut self) -> Result<FilterExpr, String> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = FilterExpr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<FilterExpr, String> {
+        let mut expr = self.unary()?;
+        while self.eat("&&") {
+            expr = FilterExpr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<FilterExpr, String> {
+        if self.eat("!") {
+            return Ok(FilterExpr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let expr = self.or()?;
+            return match self.eat(")") {
+                true => Ok(expr),
+                false => Err(self.error("expected ')'")),
+            };
+        }
+        let ident_pos = self.pos;
+        match self.take_while(|c| c.is_ascii_alphanumeric() || c == '_') {
+            "true" => Ok(FilterExpr::Const(true)),
+            "false" => Ok(FilterExpr::Const(false)),
+            "size" => self.compare(Metric::Size),
+            "support" => self.compare(Metric::Support),
+            "contains" => Ok(FilterExpr::Contains(self.item_list()?)),
+            "contains_any" => Ok(FilterExpr::ContainsAny(self.item_list()?)),
+            _ => {
+                self.pos = ident_pos;
+                self.skip_whitespace();
+                Err(self.error("expected size, support, contains, contains_any, '!' or '('"))
+            }
+        }
+    }
+
+    fn compare(&mut self, metric: Metric) -> Result<FilterExpr, String> {
+        // two-character operators first so that `<=` is not read as `<`.
+        let op = [
+            ("==", CompareOp::Eq),
+            ("!=", CompareOp::Ne),
+            ("<=", CompareOp::Le),
+            (">=", CompareOp::Ge),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt),
+        ]
+        .into_iter()
+        .find(|(token, _)| self.eat(token))
+        .map(|(_, op)| op)
+        .ok_or_else(|| self.error("expected a comparison operator"))?;
+        let value = self
+            .take_while(|c| c.is_ascii_digit())
+            .parse()
+            .map_err(|_| self.error("expected a number"))?;
+        Ok(FilterExpr::Compare(metric, op, value))
+    }
+
+    fn item_list(&mut self) -> Result<ItemMask, String> {
+        if !self.eat("(") {
+            return Err(self.error("expected '('"));
+        }
+        let items = self.take_while(|c| c != ')').to_owned();
+        if !self.eat(")") {
+            return Err(self.error("expected ')'"));
+        }
+        let items = parse_item_list(&items).map_err(|e| self.error(&e))?;
+        Ok(items.into_iter().collect())
+    }
+}
+
+/// Filter expression bound to the item-sets in which `support` is counted.
+pub struct ItemsetFilter {
+    expr: FilterExpr,
+    inputs: Vec<ItemMask>,
+}
+
+impl ItemsetFilter {
+    /// `inputs` are only kept if the expression reads `support`.
+    pub fn new<'a>(expr: FilterExpr, inputs: impl Iterator<Item = &'a [u8]>) -> Self {
+        let inputs = match expr.uses_support() {
+            true => inputs.map(|x| x.iter().copied().collect()).collect(),
+            false => Vec::new(),
+        };
+        ItemsetFilter { expr, inputs }
+    }
+
+    pub fn matches(&self, itemset: &[u8]) -> bool {
+        let mask = itemset.iter().copied().collect::<ItemMask>();
+        self.eval(&self.expr, itemset.len(), &mask)
+    }
+
+    fn eval(&self, expr: &FilterExpr, size: usize, mask: &ItemMask) -> bool {
+        match expr {
+            FilterExpr::Const(value) => *value,
+            FilterExpr::Compare(Metric::Size, op, value) => op.holds(size, *value),
+            FilterExpr::Compare(Metric::Support, op, value) => {
+                let support = self.inputs.iter().filter(|x| mask.is_subset(x)).count();
+                op.holds(support, *value)
+            }
+            FilterExpr::Contains(items) => items.is_subset(mask),
+            FilterExpr::ContainsAny(items) => items.intersects(mask),
+            FilterExpr::Not(e) => !self.eval(e, size, mask),
+            FilterExpr::And(l, r) => self.eval(l, size, mask) && self.eval(r, size, mask),
+            FilterExpr::Or(l, r) => self.eval(l, size, mask) || self.eval(r, size, mask),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn filter(expr: &str) -> ItemsetFilter {
+        let inputs: [&[u8]; 2] = [&[1, 2, 3], &[2, 3, 4]];
+        ItemsetFilter::new(expr.parse().unwrap(), inputs.into_iter())
+    }
+
+    #[test]
+    fn test_size_and_contains() {
+        let f = filter("size >= 2 && contains(2) && !contains_any(10,11)");
+        assert!(f.matches(&[2, 3]));
+        assert!(!f.matches(&[2]));
+        assert!(!f.matches(&[2, 10]));
+        assert!(!f.matches(&[1, 3]));
+    }
+
+    #[test]
+    fn test_precedence() {
+        let f = filter("contains(1) || contains(4) && size == 1");
+        assert!(f.matches(&[1, 2]));
+        assert!(f.matches(&[4]));
+        assert!(!f.matches(&[3, 4]));
+        let f = filter("(contains(1) || contains(4)) && size == 1");
+        assert!(!f.matches(&[1, 2]));
+    }
+
+    #[test]
+    fn test_support() {
+        let f = filter("support >= 2");
+        assert!(f.matches(&[2, 3]));
+        assert!(!f.matches(&[1, 2]));
+        assert!(filter("support == 0").matches(&[1, 4]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("size >> 2".parse::<FilterExpr>().is_err());
+        assert!("contains(300)".parse::<FilterExpr>().is_err());
+        assert!("size > 2 &&".parse::<FilterExpr>().is_err());
+        assert!("(size > 2".parse::<FilterExpr>().is_err());
+        assert!("weight > 2".parse::<FilterExpr>().is_err());
+    }
+}
@@ -0,0 +1,78 @@
+/// Set of u8 items as a 256-bit mask.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ItemMask([u128; 2]);
+
+impl ItemMask {
+    #[inline]
+    pub fn insert(&mut self, item: u8) {
+        self.0[(item >> 7) as usize] |= 1 << (item & 127);
+    }
+
+    #[inline]
+    pub fn contains(&self, item: u8) -> bool {
+        (self.0[(item >> 7) as usize] >> (item & 127)) & 1 == 1
+    }
+
+    pub fn len(&self) -> usize {
+        (self.0[0].count_ones() + self.0[1].count_ones()) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == [0, 0]
+    }
+
+    pub fn is_subset(&self, other: &ItemMask) -> bool {
+        (self.0[0] & !other.0[0]) | (self.0[1] & !other.0[1]) == 0
+    }
+
+    pub fn intersects(&self, other: &ItemMask) -> bool {
+        (self.0[0] & other.0[0]) | (self.0[1] & other.0[1]) != 0
+    }
+
+    pub fn intersection(&self, other: &ItemMask) -> ItemMask {
+        ItemMask([self.0[0] & other.0[0], self.0[1] & other.0[1]])
+    }
+
+    /// Items of the mask in increasing order.
+    pub fn items(&self) -> Vec<u8> {
+        (0..=u8::MAX).filter(|&x| self.contains(x)).collect()
+    }
+}
+
+impl FromIterator<u8> for ItemMask {
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Self {
+        let mut mask = ItemMask::default();
+        iter.into_iter().for_each(|item| mask.insert(item));
+        mask
+    }
+}
+
+/// Parses item lists like `17`, `1,2,3` or `40-45,50`.
+pub fn parse_item_list(s: &str) -> Result<Vec<u8>, String> {
+    let parse_item = |x: &str| {
+        x.trim()
+            .parse::<u8>()
+            .map_err(|_| format!("invalid item '{}' (items are 0..=255)", x))
+    };
+    let mut items = Vec::new();
+    for part in s.split(',') {
+        match part.split_once('-') {
+            Some((from, to)) => items.extend(parse_item(from)?..=parse_item(to)?),
+            None => items.push(parse_item(part)?),
+        }
+    }
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_item_list() {
+        assert_eq!(parse_item_list("17"), Ok(vec![17]));
+        assert_eq!(parse_item_list("40-43,50"), Ok(vec![40, 41, 42, 43, 50]));
+        assert!(parse_item_list("300").is_err());
+    }
+}
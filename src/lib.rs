@@ -0,0 +1,18 @@
+//! Library side of the expander: the filter language of `--filter`, to select item-sets
+//! with the same expressions outside the command line.
+//!
+//! ```
+//! use expander_rs::filter::{FilterExpr, ItemsetFilter};
+//!
+//! let expr = "size >= 2 && !contains_any(10,11) || support > 1"
+//!     .parse::<FilterExpr>()
+//!     .unwrap();
+//! let inputs: [&[u8]; 2] = [&[1, 2, 3], &[2, 3, 10]];
+//! let filter = ItemsetFilter::new(expr, inputs.into_iter());
+//! assert!(filter.matches(&[1, 2]));
+//! assert!(filter.matches(&[3]));
+//! assert!(!filter.matches(&[2, 10]));
+//! ```
+
+pub mod filter;
+pub mod items;
@@ -1,34 +1,63 @@
+mod auto;
+mod bench;
 mod expander;
+mod external;
+mod miner;
+mod output;
+mod provenance;
+mod rules;
+mod support;
+mod upward;
+mod verify;
 
-use ahash::AHasher;
-use anyhow::Result;
+use anyhow::{bail, Result};
+use auto::{AutoExpander, InputProfile};
+use bench::{BenchExpander, BenchFormat, BenchResult};
 use expander::bitman::BitManipulatorExpander;
 use expander::bitvec::BitVecExpander;
-use expander::set::SerializedSetLen;
-use expander::set::WrappedAHashSet;
+use expander::constraint::{parse_item_list, Constraints, ItemMask};
+use expander::hasher::{set_hash_seed, with_hash_set, HasherKind, IdentityHashSet};
+use expander::intersection::IntersectionClosure;
+use expander::levelwise::LevelWiseExpander;
+use expander::multiset::{Multiset, MultisetExpander};
+use expander::prune::prune_redundant_inputs;
+use expander::remap::ItemRemap;
+use expander::sequence::{has_repeated_items, SequenceExpander};
 use expander::set::WrappedBitVec;
 use expander::set::Wrappedu128;
+use expander::set::{SerializedSetLen, ToItems};
+use expander::table::{IntTable, TableKey};
+use expander::taxonomy::{GeneralizedExpander, Taxonomy};
+use expander::utility::{ItemWeights, MinUtility, Utility};
 use expander::vec::VecExpander;
-use expander::vechashonly::VecHashOnlyExpander;
-use expander::Expander;
-use fnv::{FnvHashSet, FnvHasher};
-use fxhash::{FxHashSet, FxHasher};
-use serde::Deserialize;
-use std::collections::hash_map::DefaultHasher;
-use std::collections::HashSet;
+use expander::vechashonly::{Fingerprint, VecHashOnlyExpander};
+use expander::{ExpandOptions, Expander};
+use expander_rs::filter::{FilterExpr, ItemsetFilter};
+use external::ExternalExpander;
+use fnv::FnvHasher;
+use miner::{MinSupport, Miner, SupportedItemset};
+use output::JsonSeqWriter;
+use provenance::{Coverage, Provenance};
+use rules::{RuleThresholds, RulesFormat};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::fs::File;
 use std::io::BufWriter;
 use std::io::Write;
 use std::io::{BufReader, Read};
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::Instant;
+use structopt::clap::AppSettings;
 use structopt::StructOpt;
+use support::SupportCounter;
+use upward::{Direction, Universe};
 
 #[cfg(feature = "mim-alloc")]
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone)]
 struct JsonSet {
     set: Vec<u8>,
 }
@@ -36,15 +65,32 @@ struct JsonSet {
 const ABOUT: &str = "Closed/Maximal Itemset Expander";
 
 #[derive(Debug, StructOpt)]
-#[structopt(name = "expander-rust", about = ABOUT)]
+#[structopt(name = "expander-rust", about = ABOUT, setting = AppSettings::SubcommandsNegateReqs)]
 struct Opt {
-    /// Input file in JSON format
+    /// Input file in JSON format (or a FIMI transaction database with --fimi)
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
+    /// Read the input as a FIMI transaction database (one transaction of space separated items
+    /// per line) and expand its maximal frequent item-sets mined with --min-support.
+    #[structopt(long)]
+    fimi: bool,
+    /// Minimum support of the mined item-sets, as a number of transactions or a fraction in (0, 1).
+    #[structopt(long)]
+    min_support: Option<MinSupport>,
+    /// Mine the closed frequent item-sets instead of the maximal ones.
+    #[structopt(long)]
+    closed: bool,
+    /// Transaction database in the FIMI format. Every output item-set is written with its
+    /// support in it (as {"set": [...], "support": n}) and the support of --filter counts it.
+    #[structopt(long, parse(from_os_str))]
+    transactions: Option<PathBuf>,
+    /// Optional output file for the mined item-sets with their supports, in the input JSON format.
+    #[structopt(long, parse(from_os_str))]
+    mined_output: Option<PathBuf>,
     /// Use Hash-only Vec Expander (u8 for each item - up to 256 items) which operates only on hashes.
     /// Experimental feature which can be additionally fast but also can cause collisions. Use with care.
     #[structopt(
-        short = "o",
+        short = "H",
         long,
         conflicts_with = "bit_vec_expander",
         conflicts_with = "bit_man_expander",
@@ -78,13 +124,48 @@ struct Opt {
         conflicts_with = "hash_only_expander"
     )]
     bit_vec_expander: bool,
+    /// Use Level-wise Vec Expander (u8 for each item - up to 256 items) which expands breadth-first,
+    /// streams each size level to the output and frees it. Peak memory is bounded by the two
+    /// largest adjacent levels.
+    #[structopt(
+        short = "l",
+        long,
+        conflicts_with = "bit_vec_expander",
+        conflicts_with = "bit_man_expander",
+        conflicts_with = "vec_expander",
+        conflicts_with = "hash_only_expander"
+    )]
+    level_wise_expander: bool,
+    /// Instead of expanding, output every non-empty intersection of input item-sets
+    /// (their closure under intersection, i.e. the closed item-sets of the input rows).
+    #[structopt(long)]
+    intersection_closure: bool,
+    /// Parent of every item in a category hierarchy in JSON format ({"3": 100, "100": 200}).
+    /// The expansion also replaces items by their ancestors, without item-sets holding an item
+    /// together with one of its ancestors. Transactions support the ancestors of their items.
+    #[structopt(long, parse(from_os_str))]
+    taxonomy: Option<PathBuf>,
+    /// Treat the input item-sets as ordered sequences and output their distinct subsequences.
+    #[structopt(long)]
+    sequences: bool,
+    /// With --sequences, only output the contiguous substrings of the input sequences.
+    #[structopt(long)]
+    contiguous: bool,
+    /// With --sequences, allow an item to appear several times in a sequence.
+    #[structopt(long)]
+    repeated_items: bool,
+    /// Treat repeated items of the input item-sets as quantities ([1, 1, 2] holds item 1 twice)
+    /// and output every sub-bag as [[item, count], ...]. Sizes count the units.
+    #[structopt(long)]
+    multisets: bool,
     /// Use FNVHash for Hasher (default).
     #[structopt(
         short = "f",
         long,
         conflicts_with = "fx_hasher",
         conflicts_with = "aes_hasher",
-        conflicts_with = "std_hasher"
+        conflicts_with = "std_hasher",
+        conflicts_with = "table_hasher"
     )]
     fnv_hasher: bool,
     /// Use FXHash for Hasher.
@@ -93,7 +174,8 @@ struct Opt {
         long,
         conflicts_with = "fnv_hasher",
         conflicts_with = "aes_hasher",
-        conflicts_with = "std_hasher"
+        conflicts_with = "std_hasher",
+        conflicts_with = "table_hasher"
     )]
     fx_hasher: bool,
     /// Use Rust's std Hasher (uses Google's SwissTable / HashBrown).
@@ -102,7 +184,8 @@ struct Opt {
         long,
         conflicts_with = "fnv_hasher",
         conflicts_with = "fx_hasher",
-        conflicts_with = "aes_hasher"
+        conflicts_with = "aes_hasher",
+        conflicts_with = "table_hasher"
     )]
     std_hasher: bool,
     /// Use AHash for Hasher (uses AES).
@@ -111,15 +194,211 @@ struct Opt {
         long,
         conflicts_with = "fnv_hasher",
         conflicts_with = "fx_hasher",
-        conflicts_with = "std_hasher"
+        conflicts_with = "std_hasher",
+        conflicts_with = "table_hasher"
     )]
     aes_hasher: bool,
+    /// Use open-addressing Int Table with linear probing instead of a generic HashSet.
+    /// Only for fixed-size keys (Bit Manipulator and Hash-only Vec Expanders).
+    #[structopt(
+        short = "t",
+        long,
+        conflicts_with = "fnv_hasher",
+        conflicts_with = "fx_hasher",
+        conflicts_with = "std_hasher",
+        conflicts_with = "aes_hasher"
+    )]
+    table_hasher: bool,
+    /// Optional expected number of item-sets to pre-size the Int Table.
+    #[structopt(long)]
+    expected_len: Option<usize>,
+    /// Select the Hasher by name instead of the flags above.
+    /// xxh3 and wyhash are only available by name; seeded-sip and seeded-aes use --hash-seed.
+    #[structopt(
+        long,
+        possible_values = &HasherKind::NAMES,
+        conflicts_with_all = &["fnv_hasher", "fx_hasher", "std_hasher", "aes_hasher", "table_hasher"]
+    )]
+    hasher: Option<HasherKind>,
+    /// Seed for the seeded-sip and seeded-aes Hashers.
+    #[structopt(long, default_value = "0")]
+    hash_seed: u64,
+    /// Use 128-bit hashes (two independent 64-bit hashes) in the Hash-only Vec Expander
+    /// which makes collisions negligible.
+    #[structopt(short = "w", long)]
+    wide_hashes: bool,
+    /// Expand exactly next to the Hash-only Vec Expander with every Hasher and report
+    /// the colliding hashes and the difference in the number of item-sets.
+    #[structopt(long)]
+    verify_collisions: bool,
+    /// Fraction of the input item-sets used by --verify-collisions.
+    #[structopt(long, default_value = "1.0", parse(try_from_str = verify::parse_sample))]
+    verify_sample: f64,
+    /// Stream the actual item-sets of the Hash-only Vec Expander to the output file
+    /// as soon as their hash is first inserted, instead of serializing the hashes.
+    #[structopt(long)]
+    stream_itemsets: bool,
+    /// Store the Hash-only Vec Expander's u64 hashes in a pass-through set instead of hashing them again.
+    /// Not available with the Int Table, which already stores them as they are.
+    #[structopt(short = "p", long)]
+    pass_through: bool,
+    /// Smallest item-set size to keep, the expansion does not go below it. 0 adds the empty item-set.
+    #[structopt(long, default_value = "1")]
+    min_size: usize,
+    /// Largest item-set size to keep, larger item-sets are walked through without being stored.
+    #[structopt(long)]
+    max_size: Option<usize>,
+    /// Items which every item-set must contain (e.g. 17 or 3,5-8). Can be repeated.
+    #[structopt(long, number_of_values = 1, parse(try_from_str = parse_item_list))]
+    require: Vec<Vec<u8>>,
+    /// Items which no item-set may contain (e.g. 40-45). Can be repeated.
+    #[structopt(long, number_of_values = 1, parse(try_from_str = parse_item_list))]
+    forbid: Vec<Vec<u8>>,
+    /// Group of items of which at most one may appear in an item-set (e.g. 1,2,3).
+    /// Can be repeated for several groups.
+    #[structopt(long, number_of_values = 1, parse(try_from_str = parse_item_list))]
+    at_most_one: Vec<Vec<u8>>,
+    /// Generate the subsets (down) or the supersets within the universe (up) of the input item-sets.
+    /// The size bounds and item constraints apply to the generated item-sets in both directions.
+    #[structopt(long, default_value = "down", possible_values = &Direction::NAMES)]
+    direction: Direction,
+    /// Universe of items for --direction up (e.g. 0-99), defaults to the items of the input item-sets.
+    /// Can be repeated.
+    #[structopt(long, number_of_values = 1, parse(try_from_str = parse_item_list))]
+    universe: Vec<Vec<u8>>,
+    /// Only output the item-sets matching the expression,
+    /// e.g. "size >= 3 && contains(5) && !contains_any(10,11) || support > 2"
+    /// where support is the number of input item-sets containing the item-set.
+    #[structopt(long)]
+    filter: Option<FilterExpr>,
+    /// Item weights (e.g. price or margin) in JSON format ({"1": 2.5, "7": 10}, 0 for missing items).
+    /// Every output item-set is written with the sum, min and max of its weights
+    /// (as {"set": [...], "utility": {"sum": s, "min": m, "max": M}}).
+    #[structopt(long, parse(from_os_str))]
+    weights: Option<PathBuf>,
+    /// Smallest summed weight of the item-sets to keep, the expansion does not go below it.
+    /// Requires --weights with non-negative weights.
+    #[structopt(long)]
+    min_utility: Option<f64>,
+    /// Optional output file reporting, for each input item-set, how many output item-sets it
+    /// contributed first (in input order) and how many only it covers. Every output item-set
+    /// is written with the number of input item-sets covering it (as {"set": [...], "covered_by": n}).
+    #[structopt(long, parse(from_os_str))]
+    provenance: Option<PathBuf>,
+    /// With --provenance, also write the indices of the input item-sets covering each output item-set.
+    #[structopt(long)]
+    provenance_indices: bool,
+    /// Drop the input item-sets contained in another one before expanding and expand the rest
+    /// by decreasing size, so that more of the probed subsets are already in the set.
+    #[structopt(long)]
+    prune_inputs: bool,
+    /// Renumber the items actually used to dense ids (mapped back in the output), so that sparse
+    /// ids fit the expanders with fixed-size keys. Without a selected expander, the Bit
+    /// Manipulator Expander is used when at most 128 items are left.
+    #[structopt(long)]
+    remap_items: bool,
+    /// With --remap-items, give the smallest ids to the most frequent items.
+    #[structopt(long)]
+    remap_by_frequency: bool,
+    /// Pick the expander from the input: its largest item id, number of distinct items,
+    /// item-set lengths and predicted number of expanded item-sets.
+    #[structopt(long)]
+    auto: bool,
+    /// With --auto, also expand this fraction of the input item-sets with every Hasher and use
+    /// the fastest one.
+    #[structopt(long, parse(try_from_str = verify::parse_sample))]
+    auto_sample: Option<f64>,
+    /// Memory limit (e.g. 512M or 2G) for an out-of-core Vec expansion. Item-sets are written
+    /// to sorted runs on disk once the limit is reached and deduplicated with a k-way merge.
+    /// The limit bounds the buffered item-sets (their hash table and allocations), not the
+    /// input or the process.
+    /// Forces the Vec Expander with FNV hashing, other expanders and Hashers are rejected.
+    #[structopt(long, parse(try_from_str = external::parse_memory_size))]
+    memory_limit: Option<usize>,
     /// Optional output file in JSON format.
     /// Each Expander serializes itemsets differently;
     /// - Bit Vec Expander: Vec<usize> per itemset (Human-Readable),
     /// - Vec Expander: Vec<u8> per itemset (Human-Readable),
+    /// - Level-wise Vec Expander: Vec<u8> per itemset (Human-Readable), largest first,
     /// - BitMan Expander: Vec<0..128> per itemset (Human-Readable),
-    /// - Hash-only Vec Expander: u64 Hash per itemset (pretty much useless).
+    /// - Hash-only Vec Expander: u64 (or u128 with wide hashes) Hash per itemset (pretty much useless)
+    ///   or Vec<u8> per itemset (Human-Readable) with --stream-itemsets.
+    #[structopt(short = "o", long, parse(from_os_str))]
+    output: Option<PathBuf>,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Generate association rules X -> Y with their support, confidence and lift
+    /// from support-annotated item-sets (e.g. an expansion written with --transactions).
+    Rules(RulesOpt),
+    /// Keep the support-annotated item-sets without a superset of equal support, e.g. the closed
+    /// item-sets of a family of frequent item-sets, to be expanded again.
+    Closed(ClosedOpt),
+    /// Run the expanders with the Hashers on an input several times and report the wall time,
+    /// throughput, peak memory and number of item-sets of every combination.
+    Bench(BenchOpt),
+}
+
+#[derive(Debug, StructOpt)]
+struct RulesOpt {
+    /// Downward closed item-sets with their supports in JSON format ({"set": [...], "support": n}).
+    #[structopt(parse(from_os_str))]
+    input: PathBuf,
+    /// Number of transactions for the lift, defaults to the support of the empty item-set.
+    #[structopt(long)]
+    nb_transactions: Option<usize>,
+    /// Minimum confidence of the rules.
+    #[structopt(long, default_value = "0")]
+    min_confidence: f64,
+    /// Minimum lift of the rules.
+    #[structopt(long, default_value = "0")]
+    min_lift: f64,
+    /// Output format of the rules.
+    #[structopt(long, default_value = "json", possible_values = &RulesFormat::NAMES)]
+    format: RulesFormat,
+    /// Optional output file, the rules are written to stdout otherwise.
+    #[structopt(short = "o", long, parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+struct ClosedOpt {
+    /// Item-sets with their supports in JSON format ({"set": [...], "support": n}).
+    #[structopt(parse(from_os_str))]
+    input: PathBuf,
+    /// Optional output file, the closed item-sets are written to stdout otherwise.
+    #[structopt(short = "o", long, parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+struct BenchOpt {
+    /// Input file in JSON format.
+    #[structopt(parse(from_os_str))]
+    input: PathBuf,
+    /// Expanders to run (e.g. vec,bit-man), all of them by default.
+    #[structopt(long, use_delimiter = true, possible_values = &BenchExpander::NAMES)]
+    expanders: Vec<BenchExpander>,
+    /// Hashers to run (e.g. fx,table), fnv, fx, std and aes by default. The Int Table only runs
+    /// with the Bit Manipulator and Hash-only Vec Expanders, the other combinations are skipped.
+    #[structopt(long, use_delimiter = true, possible_values = &HasherKind::NAMES)]
+    hashers: Vec<HasherKind>,
+    /// Number of runs of every combination.
+    #[structopt(long, default_value = "3")]
+    runs: usize,
+    /// Smallest item-set size to keep, as for the expansion.
+    #[structopt(long, default_value = "1")]
+    min_size: usize,
+    /// Largest item-set size to keep, as for the expansion.
+    #[structopt(long)]
+    max_size: Option<usize>,
+    /// Output format of the report.
+    #[structopt(long, default_value = "table", possible_values = &BenchFormat::NAMES)]
+    format: BenchFormat,
+    /// Optional output file, the report is written to stdout otherwise.
     #[structopt(short = "o", long, parse(from_os_str))]
     output: Option<PathBuf>,
 }
@@ -139,107 +418,912 @@ fn write_to_file(contents: &[u8], filepath: &Path) -> Result<()> {
     Ok(())
 }
 
+impl Opt {
+    fn expand_options(&self, weights: Option<&ItemWeights>) -> Result<ExpandOptions> {
+        let min_utility = match (self.min_utility, weights) {
+            (None, _) => None,
+            (Some(_), None) => bail!("--min-utility requires --weights"),
+            (Some(min_utility), Some(weights)) => {
+                Some(MinUtility::new(weights.clone(), min_utility)?)
+            }
+        };
+        check_size_range(self.min_size, self.max_size)?;
+        Ok(ExpandOptions {
+            min_size: self.min_size,
+            max_size: self.max_size.unwrap_or(usize::MAX),
+            constraints: Constraints {
+                required: self.require.iter().flatten().copied().collect(),
+                forbidden: self.forbid.iter().flatten().copied().collect(),
+                at_most_one: self
+                    .at_most_one
+                    .iter()
+                    .map(|group| group.iter().copied().collect())
+                    .collect(),
+            },
+            min_utility,
+        })
+    }
+
+    /// Whether no expander is selected, i.e. the default Bit Vec Expander is used.
+    fn uses_default_expander(&self) -> bool {
+        !(self.vec_expander
+            || self.hash_only_expander
+            || self.bit_vec_expander
+            || self.bit_man_expander
+            || self.level_wise_expander
+            || self.intersection_closure)
+            && self.memory_limit.is_none()
+    }
+
+    /// Whether a Hasher is selected instead of the default FNV.
+    fn selects_hasher(&self) -> bool {
+        self.hasher.is_some()
+            || self.fnv_hasher
+            || self.fx_hasher
+            || self.std_hasher
+            || self.aes_hasher
+            || self.table_hasher
+    }
+
+    fn hasher_kind(&self) -> Result<HasherKind> {
+        let hasher = match (
+            self.hasher,
+            self.fnv_hasher,
+            self.fx_hasher,
+            self.std_hasher,
+            self.aes_hasher,
+            self.table_hasher,
+        ) {
+            (Some(hasher), false, false, false, false, false) => hasher,
+            (None, _, false, false, false, false) => HasherKind::Fnv,
+            (None, false, true, false, false, false) => HasherKind::Fx,
+            (None, false, false, true, false, false) => HasherKind::Std,
+            (None, false, false, false, true, false) => HasherKind::AHash,
+            (None, false, false, false, false, true) => HasherKind::Table,
+            _ => bail!("Only one Hasher can be selected"),
+        };
+        Ok(hasher)
+    }
+}
+
 fn main() -> Result<()> {
-    let opt = Opt::from_args();
-    let contents = read_file(&opt.input);
-    let parsed_set: Vec<JsonSet> = serde_json::from_str(&contents?)?;
-    let boxed_set = work(&opt, parsed_set);
+    let mut opt = Opt::from_args();
+    set_hash_seed(opt.hash_seed);
+    match &opt.command {
+        Some(Command::Rules(rules_opt)) => return work_rules(rules_opt),
+        Some(Command::Closed(closed_opt)) => return work_closed(closed_opt),
+        Some(Command::Bench(bench_opt)) => return work_bench(bench_opt),
+        None => {}
+    }
+    let Some(input) = &opt.input else {
+        bail!("An input file is required unless a subcommand is used");
+    };
+    let contents = read_file(input)?;
+    let taxonomy = match &opt.taxonomy {
+        Some(path) => Some(Taxonomy::from_json(&read_file(path)?)?),
+        None => None,
+    };
+    let mut transactions = match &opt.transactions {
+        Some(path) => Some(miner::parse_fimi(&read_file(path)?)?),
+        None => None,
+    };
+    if let (Some(taxonomy), Some(transactions)) = (&taxonomy, transactions.as_mut()) {
+        for transaction in transactions.iter_mut() {
+            *transaction = taxonomy.with_ancestors(transaction);
+        }
+    }
+    let (parsed_set, input_transactions) = match opt.fimi {
+        false => (serde_json::from_str::<Vec<JsonSet>>(&contents)?, None),
+        true => {
+            let input_transactions = miner::parse_fimi(&contents)?;
+            (mine(&opt, &input_transactions)?, Some(input_transactions))
+        }
+    };
+    let filter = itemset_filter(
+        &opt,
+        &parsed_set,
+        transactions.as_deref().or(input_transactions.as_deref()),
+        taxonomy.as_ref(),
+    );
+    let supports = transactions.as_deref().map(SupportCounter::new);
+    let weights = match &opt.weights {
+        Some(path) => Some(ItemWeights::from_json(&read_file(path)?)?),
+        None => None,
+    };
+    let options = opt.expand_options(weights.as_ref())?;
+    let universe = match (opt.direction, opt.universe.is_empty()) {
+        (Direction::Down, true) => None,
+        (Direction::Down, false) => bail!("--universe is only used with --direction up"),
+        (Direction::Up, true) => Some(Universe::from_input(&parsed_set)),
+        (Direction::Up, false) => Some(Universe::new(opt.universe.iter().flatten().copied())),
+    };
+    if opt.intersection_closure && universe.is_some() {
+        bail!("The intersection closure cannot be combined with --direction up");
+    }
+    if taxonomy.is_some() && (opt.intersection_closure || universe.is_some()) {
+        bail!("A taxonomy cannot be combined with the intersection closure or --direction up");
+    }
+    if taxonomy.is_some() && options.min_utility.is_some() {
+        bail!("A minimum utility is not supported with a taxonomy");
+    }
+    if opt.multisets {
+        if opt.intersection_closure || universe.is_some() || taxonomy.is_some() {
+            bail!("Multisets cannot be combined with the intersection closure, --direction up or a taxonomy");
+        }
+        if filter.is_some() || supports.is_some() {
+            bail!("Filtering and supports are only available for item-sets, not multisets");
+        }
+    }
+    if (opt.contiguous || opt.repeated_items) && !opt.sequences {
+        bail!("--contiguous and --repeated-items are only used with --sequences");
+    }
+    if opt.sequences {
+        if opt.intersection_closure || universe.is_some() || taxonomy.is_some() {
+            bail!("Sequences cannot be combined with the intersection closure, --direction up or a taxonomy");
+        }
+        if supports.is_some() || opt.filter.as_ref().is_some_and(FilterExpr::uses_support) {
+            bail!("Supports are only counted for item-sets, not sequences");
+        }
+        if opt.multisets {
+            bail!("Sequences cannot be combined with --multisets");
+        }
+        if let Some(i) = parsed_set.iter().find(|x| has_repeated_items(&x.set)) {
+            if !opt.repeated_items {
+                bail!(
+                    "Sequence {:?} repeats items, allow it with --repeated-items",
+                    i.set
+                );
+            }
+        }
+    }
+    if opt.memory_limit.is_some() {
+        if opt.hash_only_expander
+            || opt.bit_vec_expander
+            || opt.bit_man_expander
+            || opt.level_wise_expander
+            || opt.intersection_closure
+            || taxonomy.is_some()
+            || opt.sequences
+            || opt.multisets
+            || opt.auto
+        {
+            bail!("--memory-limit forces the out-of-core Vec expansion, it cannot be combined with another expander");
+        }
+        if opt.selects_hasher() || opt.wide_hashes || opt.pass_through || opt.stream_itemsets {
+            bail!("--memory-limit forces the out-of-core Vec expansion, it cannot be combined with another Hasher or the Hash-only options");
+        }
+    }
+    if opt.provenance_indices && opt.provenance.is_none() {
+        bail!("--provenance-indices is only used with --provenance");
+    }
+    let provenance = match &opt.provenance {
+        Some(_) if universe.is_some() || taxonomy.is_some() || opt.sequences || opt.multisets => {
+            bail!("The provenance is only reported for the downward closure of item-sets")
+        }
+        Some(_) => Some(Provenance::new(
+            parsed_set.iter().map(|x| x.set.as_slice()),
+            opt.provenance_indices,
+        )),
+        None => None,
+    };
+    let (parsed_set, options) = match &universe {
+        Some(universe) => universe.complement_expansion(parsed_set, &options)?,
+        None => (parsed_set, options),
+    };
+    let parsed_set = match opt.prune_inputs {
+        true if opt.intersection_closure || opt.sequences || opt.multisets => {
+            bail!("Inputs contained in another one are only redundant for the closure of item-sets")
+        }
+        true => {
+            let (parsed_set, nb_dropped) = prune_redundant_inputs(parsed_set);
+            println!(
+                "Pruned {} redundant input item-sets, {} left",
+                nb_dropped,
+                parsed_set.len()
+            );
+            parsed_set
+        }
+        false => parsed_set,
+    };
+    if opt.verify_collisions {
+        if opt.intersection_closure
+            || taxonomy.is_some()
+            || opt.sequences
+            || opt.multisets
+            || opt.memory_limit.is_some()
+            || opt.auto
+            || provenance.is_some()
+        {
+            bail!("--verify-collisions only compares the expansion of item-sets, it cannot be combined with another mode, --memory-limit, --auto or --provenance");
+        }
+        let nb_total = parsed_set.len();
+        let sampled_set = verify::sample_input(parsed_set, opt.verify_sample);
+        let reports = match opt.wide_hashes {
+            false => verify::verify_collisions::<u64>(&sampled_set, &options)?,
+            true => verify::verify_collisions::<u128>(&sampled_set, &options)?,
+        };
+        verify::print_reports(&reports, sampled_set.len(), nb_total);
+        return Ok(());
+    }
+    if opt.auto_sample.is_some() && !opt.auto {
+        bail!("--auto-sample is only used with --auto");
+    }
+    let auto_expander = match opt.auto {
+        true if !opt.uses_default_expander()
+            || taxonomy.is_some()
+            || opt.sequences
+            || opt.multisets =>
+        {
+            bail!(
+                "--auto picks among the item-set expanders, it cannot be combined with another one"
+            )
+        }
+        true => {
+            let profile = InputProfile::new(&parsed_set, &options);
+            let expander = profile.pick_expander();
+            profile.print(expander);
+            match expander {
+                AutoExpander::BitMan { remap } => {
+                    opt.bit_man_expander = true;
+                    opt.remap_items |= remap;
+                }
+                AutoExpander::Vec => opt.vec_expander = true,
+                AutoExpander::LevelWise => opt.level_wise_expander = true,
+            }
+            Some(expander)
+        }
+        false => None,
+    };
+    if opt.remap_by_frequency && !opt.remap_items {
+        bail!("--remap-by-frequency is only used with --remap-items");
+    }
+    let (parsed_set, options, remap) = match opt.remap_items {
+        true if taxonomy.is_some() || opt.sequences || opt.multisets => {
+            bail!("Items are only remapped for item-sets, not with a taxonomy, sequences or multisets")
+        }
+        true => {
+            let remap = ItemRemap::new(
+                &parsed_set,
+                options.constraints.required.items(),
+                opt.remap_by_frequency,
+            );
+            println!("Remapped {} distinct items to dense ids", remap.len());
+            let options = remap.remap_options(&options);
+            (remap.remap_input(parsed_set), options, Some(remap))
+        }
+        false => (parsed_set, options, None),
+    };
+    if remap.as_ref().is_some_and(|x| x.len() <= 128) && opt.uses_default_expander() {
+        println!("Using the Bit Manipulator Expander");
+        opt.bit_man_expander = true;
+    }
+    if let (Some(expander), Some(sample)) = (auto_expander, opt.auto_sample) {
+        if opt.selects_hasher() {
+            bail!("--auto-sample picks the Hasher, it cannot be combined with another one");
+        }
+        let sampled_set = verify::sample_input(parsed_set.clone(), sample);
+        let hasher = expander.pick_hasher(&sampled_set, &options)?;
+        println!("Auto: using the {} Hasher", hasher.name());
+        opt.hasher = Some(hasher);
+    }
+    let postprocess = ItemsetOutput {
+        remap,
+        universe,
+        filter,
+        supports,
+        weights,
+        provenance,
+    };
+    dispatch(&opt, parsed_set, &options, taxonomy.as_ref(), &postprocess)?;
+    if let (Some(provenance), Some(report_path)) = (&postprocess.provenance, &opt.provenance) {
+        provenance.write_report(report_path)?;
+    }
+    Ok(())
+}
+
+/// The support of the filter counts the transactions if there are any, the input item-sets
+/// otherwise, with the ancestors of their items under a taxonomy so that generalized item-sets
+/// are counted too (the `--transactions` ones already hold them).
+fn itemset_filter(
+    opt: &Opt,
+    parsed_set: &[JsonSet],
+    transactions: Option<&[Vec<u8>]>,
+    taxonomy: Option<&Taxonomy>,
+) -> Option<ItemsetFilter> {
+    let expr = opt.filter.clone()?;
+    let inputs = match transactions {
+        Some(transactions) => transactions.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+        None => parsed_set.iter().map(|x| x.set.as_slice()).collect(),
+    };
+    let filter = match taxonomy {
+        Some(taxonomy) if opt.transactions.is_none() => {
+            let generalized = inputs
+                .into_iter()
+                .map(|x| taxonomy.with_ancestors(x))
+                .collect::<Vec<_>>();
+            ItemsetFilter::new(expr, generalized.iter().map(Vec::as_slice))
+        }
+        _ => ItemsetFilter::new(expr, inputs.into_iter()),
+    };
+    Some(filter)
+}
+
+/// Runs the expansion selected by the options and writes the output item-sets.
+fn dispatch(
+    opt: &Opt,
+    parsed_set: Vec<JsonSet>,
+    options: &ExpandOptions,
+    taxonomy: Option<&Taxonomy>,
+    postprocess: &ItemsetOutput,
+) -> Result<()> {
+    if opt.intersection_closure {
+        return work_intersection_closure(opt, parsed_set, options, postprocess);
+    }
+    if let Some(taxonomy) = taxonomy {
+        return work_generalized(opt, parsed_set, taxonomy, options, postprocess);
+    }
+    if opt.multisets {
+        return work_multisets(opt, parsed_set, options, postprocess);
+    }
+    if opt.sequences {
+        return work_sequences(opt, parsed_set, options, postprocess);
+    }
+    if let Some(memory_limit) = opt.memory_limit {
+        return work_external(opt, parsed_set, options, memory_limit, postprocess);
+    }
+    if opt.level_wise_expander {
+        return work_level_wise(opt, parsed_set, options, postprocess);
+    }
+    let boxed_set = work(opt, parsed_set, options, postprocess)?;
     println!("Total nb of item-sets: {}", boxed_set.set_len());
-    if let Some(output_path) = opt.output {
-        let boxed_set_str = serde_json::to_string(&boxed_set)?;
-        write_to_file(boxed_set_str.as_bytes(), &output_path)?;
+    match (postprocess.is_identity(), opt.stream_itemsets) {
+        (false, false) => {
+            let len = write_postprocessed(boxed_set.as_ref(), postprocess, opt.output.as_deref())?;
+            if postprocess.filter.is_some() {
+                println!("Filtered item-sets: {}", len);
+            }
+        }
+        (true, false) => {
+            if let Some(output_path) = &opt.output {
+                let boxed_set_str = serde_json::to_string(&boxed_set)?;
+                write_to_file(boxed_set_str.as_bytes(), output_path)?;
+            }
+        }
+        (_, true) => {}
     }
     Ok(())
 }
 
-fn work(opt: &Opt, parsed_set: Vec<JsonSet>) -> Box<dyn SerializedSetLen> {
-    match (
+/// Turns the expanded item-sets into the output ones: mapped back to the original ids,
+/// complemented back for the upward closure, filtered, then annotated.
+struct ItemsetOutput {
+    remap: Option<ItemRemap>,
+    universe: Option<Universe>,
+    filter: Option<ItemsetFilter>,
+    supports: Option<SupportCounter>,
+    weights: Option<ItemWeights>,
+    provenance: Option<Provenance>,
+}
+
+/// Output item-set, annotated with its support when transactions are given and with its
+/// utility when item weights are given.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum OutputRecord<'a> {
+    Itemset(Cow<'a, [u8]>),
+    Annotated {
+        set: Cow<'a, [u8]>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        support: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        utility: Option<Utility>,
+        #[serde(flatten, skip_serializing_if = "Option::is_none")]
+        coverage: Option<Coverage>,
+    },
+}
+
+/// Output bag, annotated with its utility (weights counted once per unit) when item weights
+/// are given.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum MultisetRecord<'a> {
+    Multiset(&'a Multiset),
+    Annotated { set: &'a Multiset, utility: Utility },
+}
+
+impl ItemsetOutput {
+    fn is_identity(&self) -> bool {
+        self.remap.is_none()
+            && self.universe.is_none()
+            && self.filter.is_none()
+            && self.supports.is_none()
+            && self.weights.is_none()
+            && self.provenance.is_none()
+    }
+
+    /// Output record of an expanded item-set, `None` if it is filtered out.
+    fn map<'a>(&self, itemset: &'a [u8]) -> Option<OutputRecord<'a>> {
+        let itemset = match &self.remap {
+            Some(remap) => Cow::Owned(remap.original(itemset)),
+            None => Cow::Borrowed(itemset),
+        };
+        let itemset = match &self.universe {
+            Some(universe) => Cow::Owned(universe.complement(&itemset)),
+            None => itemset,
+        };
+        if !self
+            .filter
+            .as_ref()
+            .is_none_or(|filter| filter.matches(&itemset))
+        {
+            return None;
+        }
+        if self.supports.is_none() && self.weights.is_none() && self.provenance.is_none() {
+            return Some(OutputRecord::Itemset(itemset));
+        }
+        Some(OutputRecord::Annotated {
+            support: self.supports.as_ref().map(|x| x.support(&itemset)),
+            utility: self.weights.as_ref().map(|x| x.utility(&itemset)),
+            coverage: self.provenance.as_ref().map(|x| x.record(&itemset)),
+            set: itemset,
+        })
+    }
+}
+
+/// Serializes the output item-sets of `boxed_set` and returns their number.
+fn write_postprocessed(
+    boxed_set: &dyn SerializedSetLen,
+    postprocess: &ItemsetOutput,
+    output_path: Option<&Path>,
+) -> Result<usize> {
+    let Some(itemsets) = boxed_set.itemsets() else {
+        bail!(
+            "Filtering, remapping, upward closure, supports and utilities require the actual item-sets, not their hashes"
+        );
+    };
+    let mut writer = output_path.map(JsonSeqWriter::create).transpose()?;
+    let mut len = 0;
+    for itemset in itemsets {
+        if let Some(itemset) = postprocess.map(&itemset) {
+            if let Some(writer) = writer.as_mut() {
+                writer.write_item(&itemset)?;
+            }
+            len += 1;
+        }
+    }
+    if let Some(writer) = writer {
+        writer.finish()?;
+    }
+    Ok(len)
+}
+
+fn work_rules(rules_opt: &RulesOpt) -> Result<()> {
+    let itemsets: Vec<SupportedItemset> = serde_json::from_str(&read_file(&rules_opt.input)?)?;
+    let thresholds = RuleThresholds {
+        min_confidence: rules_opt.min_confidence,
+        min_lift: rules_opt.min_lift,
+    };
+    let rules = rules::generate_rules(&itemsets, rules_opt.nb_transactions, &thresholds)?;
+    match &rules_opt.output {
+        Some(output_path) => {
+            let file = BufWriter::new(File::create(output_path)?);
+            rules::write_rules(&rules, rules_opt.format, file)?;
+            println!("Total nb of rules: {}", rules.len());
+        }
+        None => rules::write_rules(&rules, rules_opt.format, std::io::stdout().lock())?,
+    }
+    Ok(())
+}
+
+fn work_closed(closed_opt: &ClosedOpt) -> Result<()> {
+    let itemsets: Vec<SupportedItemset> = serde_json::from_str(&read_file(&closed_opt.input)?)?;
+    let nb_itemsets = itemsets.len();
+    let closed = miner::closed_itemsets(itemsets);
+    match &closed_opt.output {
+        Some(output_path) => {
+            write_to_file(serde_json::to_string(&closed)?.as_bytes(), output_path)?;
+            println!(
+                "Total nb of closed item-sets: {} (from {})",
+                closed.len(),
+                nb_itemsets
+            );
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            serde_json::to_writer(&mut stdout, &closed)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Rejects a `--min-size` above `--max-size`, which would expand nothing.
+fn check_size_range(min_size: usize, max_size: Option<usize>) -> Result<()> {
+    match max_size {
+        Some(max_size) if min_size > max_size => bail!(
+            "--min-size ({}) cannot be greater than --max-size ({})",
+            min_size,
+            max_size
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Runs every selected expander with every selected Hasher it supports.
+fn work_bench(bench_opt: &BenchOpt) -> Result<()> {
+    if bench_opt.runs == 0 {
+        bail!("The benchmark needs at least one run");
+    }
+    check_size_range(bench_opt.min_size, bench_opt.max_size)?;
+    let parsed_set: Vec<JsonSet> = serde_json::from_str(&read_file(&bench_opt.input)?)?;
+    let expanders = match bench_opt.expanders.is_empty() {
+        true => BenchExpander::ALL.to_vec(),
+        false => bench_opt.expanders.clone(),
+    };
+    let hashers = match bench_opt.hashers.is_empty() {
+        true => vec![
+            HasherKind::Fnv,
+            HasherKind::Fx,
+            HasherKind::Std,
+            HasherKind::AHash,
+        ],
+        false => bench_opt.hashers.clone(),
+    };
+    let options = ExpandOptions {
+        min_size: bench_opt.min_size,
+        max_size: bench_opt.max_size.unwrap_or(usize::MAX),
+        ..ExpandOptions::default()
+    };
+    let mut results = Vec::new();
+    for &expander in &expanders {
+        for &hasher in &hashers {
+            if !expander.supports(hasher) {
+                eprintln!(
+                    "Skipped {} with {}: the Int Table only supports the Bit Manipulator and Hash-only Vec Expanders",
+                    expander.name(),
+                    hasher.name()
+                );
+                continue;
+            }
+            let mut times = Vec::with_capacity(bench_opt.runs);
+            let mut peak_memory_kb = None;
+            let mut len = 0;
+            for _ in 0..bench_opt.runs {
+                let input = parsed_set.clone();
+                let can_reset = bench::reset_peak_memory();
+                let start = Instant::now();
+                len = expander.run(hasher, input, &options)?;
+                times.push(start.elapsed());
+                if can_reset {
+                    peak_memory_kb = peak_memory_kb.max(bench::peak_memory_kb());
+                }
+            }
+            results.push(BenchResult::new(
+                expander,
+                hasher,
+                &times,
+                peak_memory_kb,
+                len,
+            ));
+        }
+    }
+    match &bench_opt.output {
+        Some(output_path) => {
+            let file = BufWriter::new(File::create(output_path)?);
+            bench::write_results(&results, bench_opt.format, file)?;
+            println!("Total nb of benchmarked combinations: {}", results.len());
+        }
+        None => bench::write_results(&results, bench_opt.format, std::io::stdout().lock())?,
+    }
+    Ok(())
+}
+
+/// Mines the maximal (or closed) frequent item-sets of the transactions to expand them.
+fn mine(opt: &Opt, transactions: &[Vec<u8>]) -> Result<Vec<JsonSet>> {
+    let Some(min_support) = opt.min_support else {
+        bail!("Mining a FIMI transaction database requires --min-support");
+    };
+    let min_support = min_support.count(transactions.len());
+    let mined = Miner::new(transactions, min_support).mine(!opt.closed);
+    println!(
+        "Mined {} {} item-sets from {} transactions (min support {})",
+        mined.len(),
+        if opt.closed { "closed" } else { "maximal" },
+        transactions.len(),
+        min_support
+    );
+    if let Some(mined_output) = &opt.mined_output {
+        write_to_file(serde_json::to_string(&mined)?.as_bytes(), mined_output)?;
+    }
+    Ok(mined.into_iter().map(|x| JsonSet { set: x.set }).collect())
+}
+
+fn work(
+    opt: &Opt,
+    parsed_set: Vec<JsonSet>,
+    options: &ExpandOptions,
+    postprocess: &ItemsetOutput,
+) -> Result<Box<dyn SerializedSetLen>> {
+    let hasher_kind = opt.hasher_kind()?;
+    if (opt.pass_through || opt.wide_hashes || opt.stream_itemsets) && !opt.hash_only_expander {
+        bail!(
+            "Pass-through set, wide hashes and streaming are only available for the Hash-only Vec Expander"
+        );
+    }
+    if !postprocess.is_identity() && opt.hash_only_expander && !opt.stream_itemsets {
+        bail!("Filtering, remapping, upward closure, supports and utilities with the Hash-only Vec Expander require --stream-itemsets");
+    }
+    let boxed_set: Box<dyn SerializedSetLen> = match (
         opt.vec_expander,
         opt.hash_only_expander,
         opt.bit_vec_expander,
         opt.bit_man_expander,
     ) {
-        (true, false, false, false) => match (
-            opt.fnv_hasher,
-            opt.fx_hasher,
-            opt.std_hasher,
-            opt.aes_hasher,
-        ) {
-            (_, false, false, false) => {
-                Box::new(VecExpander::<FnvHashSet<Vec<u8>>>::expand(parsed_set))
-            }
-            (false, true, false, false) => {
-                Box::new(VecExpander::<FxHashSet<Vec<u8>>>::expand(parsed_set))
-            }
-            (false, false, true, false) => {
-                Box::new(VecExpander::<HashSet<Vec<u8>>>::expand(parsed_set))
-            }
-            (false, false, false, true) => {
-                Box::new(VecExpander::<WrappedAHashSet<Vec<u8>>>::expand(parsed_set))
-            }
-            _ => unreachable!(),
+        (true, false, false, false) => with_hash_set!(hasher_kind, Set, _H => {
+            Box::new(VecExpander::<Set<Vec<u8>>>::expand_with(parsed_set, options))
+        }),
+        (false, true, false, false) => match opt.wide_hashes {
+            false => work_hash_only::<u64>(opt, hasher_kind, parsed_set, options, postprocess)?,
+            true => work_hash_only::<u128>(opt, hasher_kind, parsed_set, options, postprocess)?,
         },
-        (false, true, false, false) => match (
-            opt.fnv_hasher,
-            opt.fx_hasher,
-            opt.std_hasher,
-            opt.aes_hasher,
-        ) {
-            (_, false, false, false) => Box::new(
-                VecHashOnlyExpander::<FnvHashSet<u64>, FnvHasher>::expand(parsed_set),
-            ),
-            (false, true, false, false) => Box::new(
-                VecHashOnlyExpander::<FxHashSet<u64>, FxHasher>::expand(parsed_set),
+        (false, false, _, false) => with_hash_set!(hasher_kind, Set, _H => {
+            Box::new(BitVecExpander::<Set<WrappedBitVec>>::expand_with(parsed_set, options))
+        }),
+        (false, false, false, true) => match hasher_kind {
+            HasherKind::Table => Box::new(
+                BitManipulatorExpander::<IntTable<Wrappedu128>>::expand_into(
+                    parsed_set,
+                    IntTable::with_expected_len(opt.expected_len.unwrap_or_default()),
+                    options,
+                ),
             ),
-            (false, false, true, false) => Box::new(VecHashOnlyExpander::<
-                HashSet<u64>,
-                DefaultHasher,
-            >::expand(parsed_set)),
-            (false, false, false, true) => {
-                Box::new(VecHashOnlyExpander::<WrappedAHashSet<u64>, AHasher>::expand(parsed_set))
-            }
-            _ => unreachable!(),
-        },
-        (false, false, _, false) => match (
-            opt.fnv_hasher,
-            opt.fx_hasher,
-            opt.std_hasher,
-            opt.aes_hasher,
-        ) {
-            (_, false, false, false) => Box::new(
-                BitVecExpander::<FnvHashSet<WrappedBitVec>>::expand(parsed_set),
-            ),
-            (false, true, false, false) => Box::new(
-                BitVecExpander::<FxHashSet<WrappedBitVec>>::expand(parsed_set),
-            ),
-            (false, false, true, false) => {
-                Box::new(BitVecExpander::<HashSet<WrappedBitVec>>::expand(parsed_set))
-            }
-            (false, false, false, true) => Box::new(
-                BitVecExpander::<WrappedAHashSet<WrappedBitVec>>::expand(parsed_set),
-            ),
-            _ => unreachable!(),
-        },
-        (false, false, false, true) => match (
-            opt.fnv_hasher,
-            opt.fx_hasher,
-            opt.std_hasher,
-            opt.aes_hasher,
-        ) {
-            (_, false, false, false) => Box::new(
-                BitManipulatorExpander::<FnvHashSet<Wrappedu128>>::expand(parsed_set),
-            ),
-            (false, true, false, false) => Box::new(
-                BitManipulatorExpander::<FxHashSet<Wrappedu128>>::expand(parsed_set),
-            ),
-            (false, false, true, false) => {
-                Box::new(BitManipulatorExpander::<HashSet<Wrappedu128>>::expand(parsed_set))
-            }
-            (false, false, false, true) => Box::new(
-                BitManipulatorExpander::<WrappedAHashSet<Wrappedu128>>::expand(parsed_set),
-            ),
-            _ => unreachable!(),
+            _ => with_hash_set!(hasher_kind, Set, _H => {
+                Box::new(BitManipulatorExpander::<Set<Wrappedu128>>::expand_with(parsed_set, options))
+            }),
         },
         _ => unreachable!(),
+    };
+    Ok(boxed_set)
+}
+
+/// Writes the post-processed item-sets streamed by `expand` to `--output` and
+/// prints how many were expanded (and kept by the filter), as `noun`.
+fn work_streaming(
+    opt: &Opt,
+    postprocess: &ItemsetOutput,
+    noun: &str,
+    expand: impl FnOnce(&mut dyn FnMut(&[u8]) -> Result<()>) -> Result<usize>,
+) -> Result<()> {
+    let mut writer = opt
+        .output
+        .as_deref()
+        .map(JsonSeqWriter::create)
+        .transpose()?;
+    let mut filtered_len = 0;
+    let len = expand(&mut |itemset| {
+        let Some(itemset) = postprocess.map(itemset) else {
+            return Ok(());
+        };
+        filtered_len += 1;
+        match writer.as_mut() {
+            Some(writer) => writer.write_item(&itemset),
+            None => Ok(()),
+        }
+    })?;
+    println!("Total nb of {}: {}", noun, len);
+    if postprocess.filter.is_some() {
+        println!("Filtered {}: {}", noun, filtered_len);
+    }
+    if let Some(writer) = writer {
+        writer.finish()?;
+    }
+    Ok(())
+}
+
+fn work_external(
+    opt: &Opt,
+    parsed_set: Vec<JsonSet>,
+    options: &ExpandOptions,
+    memory_limit: usize,
+    postprocess: &ItemsetOutput,
+) -> Result<()> {
+    let mut expander = ExternalExpander::new(memory_limit, options.clone());
+    work_streaming(opt, postprocess, "item-sets", |mut sink| {
+        expander.expand(parsed_set, &mut sink)
+    })?;
+    if expander.nb_runs() > 0 {
+        println!("Spilled to {} sorted runs on disk", expander.nb_runs());
+    }
+    Ok(())
+}
+
+fn work_level_wise(
+    opt: &Opt,
+    parsed_set: Vec<JsonSet>,
+    options: &ExpandOptions,
+    postprocess: &ItemsetOutput,
+) -> Result<()> {
+    let hasher_kind = opt.hasher_kind()?;
+    let mut largest_level_len = 0;
+    work_streaming(opt, postprocess, "item-sets", |mut sink| {
+        let stats = with_hash_set!(hasher_kind, Set, _H => {
+            LevelWiseExpander::<Set<Vec<u8>>>::expand_streaming(parsed_set, options, &mut sink)?
+        });
+        largest_level_len = stats.largest_level_len;
+        Ok(stats.len)
+    })?;
+    println!("Largest level: {} item-sets", largest_level_len);
+    Ok(())
+}
+
+fn work_intersection_closure(
+    opt: &Opt,
+    parsed_set: Vec<JsonSet>,
+    options: &ExpandOptions,
+    postprocess: &ItemsetOutput,
+) -> Result<()> {
+    let hasher_kind = opt.hasher_kind()?;
+    work_streaming(opt, postprocess, "item-sets", |mut sink| {
+        Ok(with_hash_set!(hasher_kind, Set, _H => {
+            IntersectionClosure::<Set<ItemMask>>::expand_streaming(parsed_set, options, &mut sink)?
+        }))
+    })
+}
+
+fn work_generalized(
+    opt: &Opt,
+    parsed_set: Vec<JsonSet>,
+    taxonomy: &Taxonomy,
+    options: &ExpandOptions,
+    postprocess: &ItemsetOutput,
+) -> Result<()> {
+    let hasher_kind = opt.hasher_kind()?;
+    work_streaming(opt, postprocess, "item-sets", |mut sink| {
+        Ok(with_hash_set!(hasher_kind, Set, _H => {
+            GeneralizedExpander::<Set<Vec<u8>>>::expand_streaming(parsed_set, taxonomy, options, &mut sink)?
+        }))
+    })
+}
+
+fn work_sequences(
+    opt: &Opt,
+    parsed_set: Vec<JsonSet>,
+    options: &ExpandOptions,
+    postprocess: &ItemsetOutput,
+) -> Result<()> {
+    let hasher_kind = opt.hasher_kind()?;
+    work_streaming(opt, postprocess, "sequences", |mut sink| {
+        Ok(with_hash_set!(hasher_kind, Set, _H => {
+            SequenceExpander::<Set<Vec<u8>>>::expand_streaming(parsed_set, opt.contiguous, options, &mut sink)?
+        }))
+    })
+}
+
+fn work_multisets(
+    opt: &Opt,
+    parsed_set: Vec<JsonSet>,
+    options: &ExpandOptions,
+    postprocess: &ItemsetOutput,
+) -> Result<()> {
+    let hasher_kind = opt.hasher_kind()?;
+    let mut writer = opt
+        .output
+        .as_deref()
+        .map(JsonSeqWriter::create)
+        .transpose()?;
+    let mut sink = |multiset: &Multiset| {
+        let Some(writer) = writer.as_mut() else {
+            return Ok(());
+        };
+        match &postprocess.weights {
+            Some(weights) => writer.write_item(&MultisetRecord::Annotated {
+                set: multiset,
+                utility: weights.utility(&multiset.items().collect::<Vec<_>>()),
+            }),
+            None => writer.write_item(&MultisetRecord::Multiset(multiset)),
+        }
+    };
+    let len = with_hash_set!(hasher_kind, Set, _H => {
+        MultisetExpander::<Set<Multiset>>::expand_streaming(parsed_set, options, &mut sink)?
+    });
+    println!("Total nb of multisets: {}", len);
+    if let Some(writer) = writer {
+        writer.finish()?;
+    }
+    Ok(())
+}
+
+fn work_hash_only<F>(
+    opt: &Opt,
+    hasher_kind: HasherKind,
+    parsed_set: Vec<JsonSet>,
+    options: &ExpandOptions,
+    postprocess: &ItemsetOutput,
+) -> Result<Box<dyn SerializedSetLen>>
+where
+    F: Fingerprint + TableKey + Serialize + ToItems + 'static,
+{
+    let mut writer = match (opt.stream_itemsets, &opt.output) {
+        (true, Some(output_path)) => Some(JsonSeqWriter::create(output_path)?),
+        (true, None) => bail!("Streaming item-sets requires an output file"),
+        (false, _) => None,
+    };
+    let mut filtered_len = 0;
+    let mut sink = |itemset: &[u8]| {
+        let Some(itemset) = postprocess.map(itemset) else {
+            return;
+        };
+        filtered_len += 1;
+        if let Some(writer) = writer.as_mut() {
+            writer.push(&itemset);
+        }
+    };
+    let boxed_set: Box<dyn SerializedSetLen> = match (hasher_kind, opt.pass_through) {
+        (HasherKind::Table, true) => {
+            bail!("The Int Table already stores the hashes as they are, it cannot be combined with the pass-through set")
+        }
+        (HasherKind::Table, false) => Box::new(
+            VecHashOnlyExpander::<IntTable<F>, FnvHasher, F>::expand_streaming(
+                parsed_set,
+                IntTable::with_expected_len(opt.expected_len.unwrap_or_default()),
+                options,
+                &mut sink,
+            ),
+        ),
+        (_, false) => with_hash_set!(hasher_kind, Set, H => {
+            Box::new(VecHashOnlyExpander::<Set<F>, H, F>::expand_streaming(
+                parsed_set,
+                Set::default(),
+                options,
+                &mut sink,
+            ))
+        }),
+        (_, true) => with_hash_set!(hasher_kind, _Set, H => {
+            Box::new(VecHashOnlyExpander::<IdentityHashSet<F>, H, F>::expand_streaming(
+                parsed_set,
+                IdentityHashSet::default(),
+                options,
+                &mut sink,
+            ))
+        }),
+    };
+    if let Some(writer) = writer {
+        writer.finish()?;
+        if postprocess.filter.is_some() {
+            println!("Filtered item-sets: {}", filtered_len);
+        }
+    }
+    Ok(boxed_set)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn opt(args: &[&str]) -> Opt {
+        Opt::from_iter_safe(std::iter::once("expander-rs").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn test_filter_support_with_taxonomy() {
+        let parsed_set = vec![JsonSet { set: vec![1, 2, 3] }, JsonSet { set: vec![2, 4] }];
+        let taxonomy = Taxonomy::from_json(r#"{"1": 100, "2": 100, "3": 101, "4": 101}"#).unwrap();
+        let opt = opt(&["--taxonomy", "parents.json", "--filter", "support >= 2"]);
+        let filter = itemset_filter(&opt, &parsed_set, None, Some(&taxonomy)).unwrap();
+        assert!(filter.matches(&[100, 101]));
+        assert!(filter.matches(&[2, 101]));
+        assert!(!filter.matches(&[1, 101]));
+    }
+
+    #[test]
+    fn test_min_size_above_max_size() {
+        assert!(opt(&["--min-size", "3", "--max-size", "2"])
+            .expand_options(None)
+            .is_err());
+        let options = opt(&["--min-size", "2", "--max-size", "2"])
+            .expand_options(None)
+            .unwrap();
+        assert_eq!((options.min_size, options.max_size), (2, 2));
     }
 }
@@ -0,0 +1,275 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::expander::constraint::ItemMask;
+use crate::expander::set::WrappedBitVec;
+use crate::support::{TidLists, TidSet};
+
+/// Parses a FIMI transaction database: one transaction per line, items separated by spaces.
+pub(crate) fn parse_fimi(contents: &str) -> Result<Vec<Vec<u8>>> {
+    let mut transactions = Vec::new();
+    for (line_nb, line) in contents.lines().enumerate() {
+        let mut transaction = Vec::new();
+        for token in line.split_whitespace() {
+            match token.parse::<u8>() {
+                Ok(item) => transaction.push(item),
+                Err(_) => bail!(
+                    "invalid item '{}' on line {} (items are 0..=255)",
+                    token,
+                    line_nb + 1
+                ),
+            }
+        }
+        transaction.sort_unstable();
+        transaction.dedup();
+        transactions.push(transaction);
+    }
+    Ok(transactions)
+}
+
+/// Minimum support as a number of transactions or, below 1, as a fraction of them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum MinSupport {
+    Absolute(usize),
+    Relative(f64),
+}
+
+impl MinSupport {
+    pub(crate) fn count(&self, nb_transactions: usize) -> usize {
+        match *self {
+            MinSupport::Absolute(count) => count.max(1),
+            MinSupport::Relative(fraction) => {
+                ((fraction * nb_transactions as f64).ceil() as usize).max(1)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for MinSupport {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(count) = s.parse::<usize>() {
+            return Ok(MinSupport::Absolute(count));
+        }
+        match s.parse::<f64>() {
+            Ok(fraction) if fraction > 0.0 && fraction < 1.0 => Ok(MinSupport::Relative(fraction)),
+            _ => Err(format!(
+                "invalid minimum support '{}', use a count or a fraction in (0, 1)",
+                s
+            )),
+        }
+    }
+}
+
+/// Item-set with its number of supporting transactions. Serializes like the input
+/// `JsonSet` so that mined item-sets can be expanded again.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) struct SupportedItemset {
+    pub(crate) set: Vec<u8>,
+    pub(crate) support: usize,
+}
+
+/// Keeps the item-sets without a superset of equal support in `itemsets`, e.g. the closed
+/// item-sets of a family of frequent item-sets. Within each support level the item-sets are
+/// visited by decreasing size, so any equal-support superset of an item-set leads to a kept
+/// one that contains it too. Duplicates are dropped as they contain each other.
+pub(crate) fn closed_itemsets(itemsets: Vec<SupportedItemset>) -> Vec<SupportedItemset> {
+    let mut itemsets = itemsets
+        .into_iter()
+        .map(|x| (x.set.iter().copied().collect::<ItemMask>(), x))
+        .collect::<Vec<_>>();
+    itemsets
+        .sort_by(|(a, x), (b, y)| (y.support, b.len(), &x.set).cmp(&(x.support, a.len(), &y.set)));
+    let mut closed = Vec::<(ItemMask, SupportedItemset)>::new();
+    let mut level_start = 0;
+    for (mask, itemset) in itemsets {
+        if closed
+            .get(level_start)
+            .is_some_and(|(_, x)| x.support != itemset.support)
+        {
+            level_start = closed.len();
+        }
+        if !closed[level_start..].iter().any(|(x, _)| mask.is_subset(x)) {
+            closed.push((mask, itemset));
+        }
+    }
+    closed.into_iter().map(|(_, x)| x).collect()
+}
+
+/// Closed and maximal frequent item-set miner in the style of LCM. Each frequent item has
+/// a vertical tid-set (one bit per transaction), the support of an item-set is the size of
+/// the intersection of its tid-sets, and closed item-sets are enumerated once each through
+/// prefix-preserving closure extensions.
+pub(crate) struct Miner {
+    tid_lists: TidLists<WrappedBitVec>,
+    frequent_items: Vec<u8>,
+    min_support: usize,
+}
+
+impl Miner {
+    pub(crate) fn new(transactions: &[Vec<u8>], min_support: usize) -> Self {
+        let tid_lists = TidLists::<WrappedBitVec>::new(transactions);
+        let frequent_items = (0..=u8::MAX)
+            .filter(|&item| tid_lists.tidset(item).len() >= min_support)
+            .collect();
+        Miner {
+            tid_lists,
+            frequent_items,
+            min_support,
+        }
+    }
+
+    /// Closed frequent item-sets, or only the maximal ones.
+    pub(crate) fn mine(&self, maximal_only: bool) -> Vec<SupportedItemset> {
+        let mut mined = Vec::new();
+        if self.tid_lists.nb_transactions() < self.min_support {
+            return mined;
+        }
+        let tids = WrappedBitVec::full(self.tid_lists.nb_transactions());
+        let itemset = self.closure(&tids);
+        if !itemset.is_empty() {
+            self.emit(itemset, &tids, maximal_only, &mut mined);
+        }
+        self.extend(itemset, &tids, None, maximal_only, &mut mined);
+        mined
+    }
+
+    /// Frequent items contained in every transaction of `tids`.
+    fn closure(&self, tids: &WrappedBitVec) -> ItemMask {
+        let support = tids.len();
+        self.frequent_items
+            .iter()
+            .copied()
+            .filter(|&item| tids.intersection_len(self.tid_lists.tidset(item)) == support)
+            .collect()
+    }
+
+    fn extend(
+        &self,
+        itemset: ItemMask,
+        tids: &WrappedBitVec,
+        core: Option<u8>,
+        maximal_only: bool,
+        mined: &mut Vec<SupportedItemset>,
+    ) {
+        for &item in &self.frequent_items {
+            if core.is_some_and(|core| item <= core) || itemset.contains(item) {
+                continue;
+            }
+            let mut new_tids = tids.clone();
+            new_tids.intersect_with(self.tid_lists.tidset(item));
+            if new_tids.len() < self.min_support {
+                continue;
+            }
+            let closed_itemset = self.closure(&new_tids);
+            // the closure must not add items before `item`, otherwise it is reached from another prefix.
+            let is_prefix_preserving = self
+                .frequent_items
+                .iter()
+                .take_while(|&&x| x < item)
+                .all(|&x| closed_itemset.contains(x) == itemset.contains(x));
+            if is_prefix_preserving {
+                self.emit(closed_itemset, &new_tids, maximal_only, mined);
+                self.extend(closed_itemset, &new_tids, Some(item), maximal_only, mined);
+            }
+        }
+    }
+
+    fn emit(
+        &self,
+        itemset: ItemMask,
+        tids: &WrappedBitVec,
+        maximal_only: bool,
+        mined: &mut Vec<SupportedItemset>,
+    ) {
+        let is_maximal = || {
+            self.frequent_items.iter().all(|&item| {
+                itemset.contains(item)
+                    || tids.intersection_len(self.tid_lists.tidset(item)) < self.min_support
+            })
+        };
+        if !maximal_only || is_maximal() {
+            mined.push(SupportedItemset {
+                set: itemset.items(),
+                support: tids.len(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn transactions() -> Vec<Vec<u8>> {
+        parse_fimi("1 2 3\n1 2\n2 3\n4 1 2 3\n").unwrap()
+    }
+
+    #[test]
+    fn test_parse_fimi() {
+        assert_eq!(transactions()[3], vec![1, 2, 3, 4]);
+        assert!(parse_fimi("1 2\n3 256\n").is_err());
+    }
+
+    #[test]
+    fn test_min_support() {
+        assert_eq!("3".parse(), Ok(MinSupport::Absolute(3)));
+        assert_eq!("0.5".parse::<MinSupport>().unwrap().count(5), 3);
+        assert!("1.5".parse::<MinSupport>().is_err());
+    }
+
+    #[test]
+    fn test_closed() {
+        let mut mined = Miner::new(&transactions(), 2).mine(false);
+        mined.sort_by(|a, b| a.set.cmp(&b.set));
+        let expected = [
+            (vec![1, 2], 3),
+            (vec![1, 2, 3], 2),
+            (vec![2], 4),
+            (vec![2, 3], 3),
+        ];
+        assert_eq!(mined.len(), expected.len());
+        for (itemset, (set, support)) in mined.iter().zip(expected) {
+            assert_eq!(itemset.set, set);
+            assert_eq!(itemset.support, support);
+        }
+    }
+
+    #[test]
+    fn test_closed_itemsets() {
+        let frequent = [
+            (vec![1], 3),
+            (vec![2], 4),
+            (vec![3], 3),
+            (vec![2, 1], 3),
+            (vec![1, 3], 2),
+            (vec![2, 3], 3),
+            (vec![1, 2, 3], 2),
+            (vec![3, 2], 3),
+        ]
+        .into_iter()
+        .map(|(set, support)| SupportedItemset { set, support })
+        .collect();
+        let mut closed = closed_itemsets(frequent);
+        closed.iter_mut().for_each(|x| x.set.sort_unstable());
+        closed.sort_by(|a, b| a.set.cmp(&b.set));
+        let mut mined = Miner::new(&transactions(), 2).mine(false);
+        mined.sort_by(|a, b| a.set.cmp(&b.set));
+        assert_eq!(closed, mined);
+    }
+
+    #[test]
+    fn test_maximal() {
+        let mined = Miner::new(&transactions(), 2).mine(true);
+        assert_eq!(
+            mined,
+            vec![SupportedItemset {
+                set: vec![1, 2, 3],
+                support: 2
+            }]
+        );
+        assert!(Miner::new(&transactions(), 5).mine(true).is_empty());
+    }
+}
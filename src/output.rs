@@ -0,0 +1,83 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Writes a JSON array one element at a time so that item-sets can be streamed
+/// to the output file while expanding instead of serializing a whole set at the end.
+pub(crate) struct JsonSeqWriter<W: Write> {
+    writer: W,
+    len: usize,
+    error: Option<anyhow::Error>,
+}
+
+impl JsonSeqWriter<BufWriter<File>> {
+    pub(crate) fn create(filepath: &Path) -> Result<Self> {
+        let file = File::create(filepath)?;
+        JsonSeqWriter::new(BufWriter::new(file))
+    }
+}
+
+impl<W: Write> JsonSeqWriter<W> {
+    pub(crate) fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(b"[")?;
+        Ok(JsonSeqWriter {
+            writer,
+            len: 0,
+            error: None,
+        })
+    }
+
+    pub(crate) fn write_item<T: Serialize + ?Sized>(&mut self, item: &T) -> Result<()> {
+        if self.len > 0 {
+            self.writer.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut self.writer, item)?;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Like `write_item` but keeps the first error until `finish`, for use in sinks
+    /// which cannot return errors.
+    pub(crate) fn push<T: Serialize + ?Sized>(&mut self, item: &T) {
+        if self.error.is_none() {
+            if let Err(e) = self.write_item(item) {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    /// Closes the array and returns the underlying writer.
+    pub(crate) fn finish(mut self) -> Result<W> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.writer.write_all(b"]")?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_json_seq() {
+        let mut writer = JsonSeqWriter::new(Vec::new()).unwrap();
+        writer.write_item(&[1u8, 2]).unwrap();
+        writer.push(&[3u8][..]);
+        assert_eq!(writer.len, 2);
+        let bytes = writer.finish().unwrap();
+        let parsed: Vec<Vec<u8>> = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed, vec![vec![1, 2], vec![3]]);
+    }
+
+    #[test]
+    fn test_json_seq_empty() {
+        let writer = JsonSeqWriter::new(Vec::new()).unwrap();
+        assert_eq!(writer.finish().unwrap(), b"[]");
+    }
+}
@@ -0,0 +1,146 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::cell::Cell;
+use std::path::Path;
+
+use crate::expander::constraint::ItemMask;
+use crate::output::JsonSeqWriter;
+
+/// Input item-sets covering an output item-set (i.e. containing it).
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct Coverage {
+    pub(crate) covered_by: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) inputs: Option<Vec<usize>>,
+}
+
+/// Contribution of an input item-set to the union of the expansions.
+#[derive(Debug, PartialEq, Serialize)]
+struct Contribution {
+    input: usize,
+    /// Output item-sets covered by no earlier input, i.e. new when expanding in input order.
+    contributed: usize,
+    /// Output item-sets covered by no other input.
+    exclusive: usize,
+}
+
+/// Records which inputs cover each output item-set and sums them up per input. The counts
+/// are cells as the output item-sets are recorded from the sinks of the expanders.
+pub(crate) struct Provenance {
+    inputs: Vec<ItemMask>,
+    contributed: Vec<Cell<usize>>,
+    exclusive: Vec<Cell<usize>>,
+    with_indices: bool,
+}
+
+impl Provenance {
+    pub(crate) fn new<'a>(inputs: impl Iterator<Item = &'a [u8]>, with_indices: bool) -> Self {
+        let inputs = inputs
+            .map(|x| x.iter().copied().collect())
+            .collect::<Vec<ItemMask>>();
+        Provenance {
+            contributed: vec![Cell::new(0); inputs.len()],
+            exclusive: vec![Cell::new(0); inputs.len()],
+            inputs,
+            with_indices,
+        }
+    }
+
+    pub(crate) fn record(&self, itemset: &[u8]) -> Coverage {
+        let mask = itemset.iter().copied().collect::<ItemMask>();
+        let mut covering = self
+            .inputs
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| mask.is_subset(x))
+            .map(|(i, _)| i);
+        let Some(first) = covering.next() else {
+            return Coverage {
+                covered_by: 0,
+                inputs: self.with_indices.then(Vec::new),
+            };
+        };
+        let counter = &self.contributed[first];
+        counter.set(counter.get() + 1);
+        let (covered_by, inputs) = match self.with_indices {
+            true => {
+                let inputs = std::iter::once(first).chain(covering).collect::<Vec<_>>();
+                (inputs.len(), Some(inputs))
+            }
+            false => (1 + covering.count(), None),
+        };
+        if covered_by == 1 {
+            let counter = &self.exclusive[first];
+            counter.set(counter.get() + 1);
+        }
+        Coverage { covered_by, inputs }
+    }
+
+    fn contributions(&self) -> impl Iterator<Item = Contribution> + '_ {
+        (0..self.inputs.len()).map(|i| Contribution {
+            input: i,
+            contributed: self.contributed[i].get(),
+            exclusive: self.exclusive[i].get(),
+        })
+    }
+
+    /// Writes the contribution of every input, in input order.
+    pub(crate) fn write_report(&self, filepath: &Path) -> Result<()> {
+        let mut writer = JsonSeqWriter::create(filepath)?;
+        for contribution in self.contributions() {
+            writer.write_item(&contribution)?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_provenance() {
+        let inputs = [vec![1, 2, 3], vec![2, 3, 4], vec![5]];
+        let provenance = Provenance::new(inputs.iter().map(Vec::as_slice), true);
+        // the union of the expansions of the inputs.
+        let coverages = [
+            vec![1],
+            vec![2],
+            vec![3],
+            vec![4],
+            vec![5],
+            vec![1, 2],
+            vec![1, 3],
+            vec![2, 3],
+            vec![2, 4],
+            vec![3, 4],
+            vec![1, 2, 3],
+            vec![2, 3, 4],
+        ]
+        .iter()
+        .map(|x| provenance.record(x))
+        .collect::<Vec<_>>();
+        assert_eq!(
+            coverages[7],
+            Coverage {
+                covered_by: 2,
+                inputs: Some(vec![0, 1]),
+            }
+        );
+        let contributions = provenance.contributions().collect::<Vec<_>>();
+        assert_eq!(
+            (contributions[0].contributed, contributions[0].exclusive),
+            (7, 4)
+        );
+        assert_eq!(
+            (contributions[1].contributed, contributions[1].exclusive),
+            (4, 4)
+        );
+        assert_eq!(
+            (contributions[2].contributed, contributions[2].exclusive),
+            (1, 1)
+        );
+    }
+}
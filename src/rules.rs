@@ -0,0 +1,214 @@
+use anyhow::{bail, Result};
+use fnv::FnvHashMap;
+use serde::Serialize;
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::miner::SupportedItemset;
+
+/// Association rule X → Y with the support of X ∪ Y.
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct Rule {
+    pub(crate) antecedent: Vec<u8>,
+    pub(crate) consequent: Vec<u8>,
+    pub(crate) support: usize,
+    pub(crate) confidence: f64,
+    pub(crate) lift: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RulesFormat {
+    Json,
+    Csv,
+}
+
+impl RulesFormat {
+    pub(crate) const NAMES: [&'static str; 2] = ["json", "csv"];
+}
+
+impl FromStr for RulesFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(RulesFormat::Json),
+            "csv" => Ok(RulesFormat::Csv),
+            _ => Err(format!(
+                "unknown format '{}', use one of {:?}",
+                s,
+                Self::NAMES
+            )),
+        }
+    }
+}
+
+/// Thresholds of the generated rules.
+pub(crate) struct RuleThresholds {
+    pub(crate) min_confidence: f64,
+    pub(crate) min_lift: f64,
+}
+
+/// Generates every rule X → Y with X ∪ Y an item-set of `itemsets` and X, Y non-empty.
+/// The family must be downward closed (e.g. expanded frequent item-sets annotated with
+/// their supports) so that the supports of X and Y are known. The number of transactions
+/// is needed for the lift and defaults to the support of the empty item-set if present.
+pub(crate) fn generate_rules(
+    itemsets: &[SupportedItemset],
+    nb_transactions: Option<usize>,
+    thresholds: &RuleThresholds,
+) -> Result<Vec<Rule>> {
+    let mut supports = FnvHashMap::default();
+    for itemset in itemsets {
+        let mut set = itemset.set.clone();
+        set.sort_unstable();
+        supports.insert(set, itemset.support);
+    }
+    let Some(nb_transactions) = nb_transactions.or_else(|| supports.get(&Vec::new()).copied())
+    else {
+        bail!("The number of transactions is required when the empty item-set is not given");
+    };
+    let support_of = |set: &Vec<u8>| match supports.get(set) {
+        Some(&support) => Ok(support),
+        None => bail!(
+            "The support of {:?} is missing, the item-sets must be downward closed",
+            set
+        ),
+    };
+    let mut rules = Vec::new();
+    for (set, &support) in &supports {
+        if set.len() < 2 {
+            continue;
+        }
+        if set.len() >= usize::BITS as usize {
+            bail!("Item-set {:?} is too large to generate rules from", set);
+        }
+        // every non-empty proper subset of the item-set as antecedent.
+        for mask in 1..(1usize << set.len()) - 1 {
+            let (antecedent, consequent): (Vec<_>, Vec<_>) = set
+                .iter()
+                .enumerate()
+                .partition(|(i, _)| (mask >> i) & 1 == 1);
+            let antecedent = antecedent.into_iter().map(|(_, &x)| x).collect();
+            let consequent = consequent.into_iter().map(|(_, &x)| x).collect();
+            let confidence = support as f64 / support_of(&antecedent)? as f64;
+            let lift = confidence * nb_transactions as f64 / support_of(&consequent)? as f64;
+            if confidence >= thresholds.min_confidence && lift >= thresholds.min_lift {
+                rules.push(Rule {
+                    antecedent,
+                    consequent,
+                    support,
+                    confidence,
+                    lift,
+                });
+            }
+        }
+    }
+    rules.sort_by(|a, b| (&a.antecedent, &a.consequent).cmp(&(&b.antecedent, &b.consequent)));
+    Ok(rules)
+}
+
+fn join_items(items: &[u8]) -> String {
+    items
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub(crate) fn write_rules(
+    rules: &[Rule],
+    format: RulesFormat,
+    mut writer: impl Write,
+) -> Result<()> {
+    match format {
+        RulesFormat::Json => serde_json::to_writer(&mut writer, rules)?,
+        RulesFormat::Csv => {
+            writeln!(writer, "antecedent,consequent,support,confidence,lift")?;
+            for rule in rules {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{}",
+                    join_items(&rule.antecedent),
+                    join_items(&rule.consequent),
+                    rule.support,
+                    rule.confidence,
+                    rule.lift
+                )?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn itemsets() -> Vec<SupportedItemset> {
+        [
+            (vec![1], 3),
+            (vec![2], 4),
+            (vec![3], 3),
+            (vec![1, 2], 3),
+            (vec![2, 3], 3),
+            (vec![3, 1], 2),
+            (vec![1, 2, 3], 2),
+        ]
+        .into_iter()
+        .map(|(set, support)| SupportedItemset { set, support })
+        .collect()
+    }
+
+    fn no_thresholds() -> RuleThresholds {
+        RuleThresholds {
+            min_confidence: 0.0,
+            min_lift: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_generate_rules() {
+        let rules = generate_rules(&itemsets(), Some(4), &no_thresholds()).unwrap();
+        // 3 pairs with 2 rules each and 6 rules for the triple.
+        assert_eq!(rules.len(), 12);
+        let rule = rules
+            .iter()
+            .find(|r| r.antecedent == [1] && r.consequent == [3])
+            .unwrap();
+        assert_eq!(rule.support, 2);
+        assert!((rule.confidence - 2.0 / 3.0).abs() < 1e-9);
+        assert!((rule.lift - 8.0 / 9.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let thresholds = RuleThresholds {
+            min_confidence: 1.0,
+            min_lift: 1.0,
+        };
+        let rules = generate_rules(&itemsets(), Some(4), &thresholds).unwrap();
+        assert!(rules.iter().all(|r| r.confidence >= 1.0 && r.lift >= 1.0));
+        assert_eq!(rules.len(), 3);
+    }
+
+    #[test]
+    fn test_missing_support() {
+        let mut itemsets = itemsets();
+        itemsets.retain(|x| x.set != [2]);
+        assert!(generate_rules(&itemsets, Some(4), &no_thresholds()).is_err());
+        assert!(generate_rules(&self::itemsets(), None, &no_thresholds()).is_err());
+    }
+
+    #[test]
+    fn test_csv() {
+        let rules = generate_rules(&itemsets(), Some(4), &no_thresholds()).unwrap();
+        let mut csv = Vec::new();
+        write_rules(&rules[..1], RulesFormat::Csv, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "antecedent,consequent,support,confidence,lift\n1,2,3,1,1\n"
+        );
+    }
+}
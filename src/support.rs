@@ -0,0 +1,190 @@
+use bitvec::vec::BitVec;
+
+use crate::expander::set::{WrappedBitVec, Wrappedu128};
+
+/// Set of transaction ids, reusing the item-set bitset representations.
+pub(crate) trait TidSet: Clone {
+    fn empty(nb_transactions: usize) -> Self;
+    fn full(nb_transactions: usize) -> Self;
+    fn insert(&mut self, tid: usize);
+    fn intersect_with(&mut self, other: &Self);
+    /// Number of transactions in both tid-sets, without building their intersection.
+    fn intersection_len(&self, other: &Self) -> usize;
+    fn len(&self) -> usize;
+}
+
+impl TidSet for Wrappedu128 {
+    fn empty(nb_transactions: usize) -> Self {
+        debug_assert!(nb_transactions <= u128::BITS as usize);
+        Wrappedu128(0)
+    }
+    fn full(nb_transactions: usize) -> Self {
+        debug_assert!(nb_transactions <= u128::BITS as usize);
+        Wrappedu128(
+            u128::MAX
+                .checked_shr(u128::BITS - nb_transactions as u32)
+                .unwrap_or(0),
+        )
+    }
+    #[inline]
+    fn insert(&mut self, tid: usize) {
+        self.0 |= 1 << tid;
+    }
+    #[inline]
+    fn intersect_with(&mut self, other: &Self) {
+        self.0 &= other.0;
+    }
+    #[inline]
+    fn intersection_len(&self, other: &Self) -> usize {
+        (self.0 & other.0).count_ones() as usize
+    }
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+}
+
+impl TidSet for WrappedBitVec {
+    fn empty(nb_transactions: usize) -> Self {
+        WrappedBitVec(BitVec::repeat(false, nb_transactions))
+    }
+    fn full(nb_transactions: usize) -> Self {
+        WrappedBitVec(BitVec::repeat(true, nb_transactions))
+    }
+    #[inline]
+    fn insert(&mut self, tid: usize) {
+        self.0.set(tid, true);
+    }
+    #[inline]
+    fn intersect_with(&mut self, other: &Self) {
+        self.0 &= &other.0;
+    }
+    #[inline]
+    fn intersection_len(&self, other: &Self) -> usize {
+        self.0
+            .as_raw_slice()
+            .iter()
+            .zip(other.0.as_raw_slice())
+            .map(|(l, r)| (l & r).count_ones() as usize)
+            .sum()
+    }
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.count_ones()
+    }
+}
+
+/// Vertical transaction database: one tid-set per item. The support of an item-set is the
+/// size of the intersection of the tid-sets of its items. The miner walks the same tid-sets.
+pub(crate) struct TidLists<S> {
+    tidsets: Vec<S>,
+    nb_transactions: usize,
+}
+
+impl<S: TidSet> TidLists<S> {
+    pub(crate) fn new(transactions: &[Vec<u8>]) -> Self {
+        let mut tidsets = vec![S::empty(transactions.len()); u8::MAX as usize + 1];
+        for (tid, transaction) in transactions.iter().enumerate() {
+            for &item in transaction {
+                tidsets[item as usize].insert(tid);
+            }
+        }
+        TidLists {
+            tidsets,
+            nb_transactions: transactions.len(),
+        }
+    }
+
+    pub(crate) fn nb_transactions(&self) -> usize {
+        self.nb_transactions
+    }
+
+    #[inline]
+    pub(crate) fn tidset(&self, item: u8) -> &S {
+        &self.tidsets[item as usize]
+    }
+
+    pub(crate) fn support(&self, itemset: &[u8]) -> usize {
+        let Some((&first, rest)) = itemset.split_first() else {
+            return self.nb_transactions;
+        };
+        let mut tids = self.tidsets[first as usize].clone();
+        for &item in rest {
+            tids.intersect_with(&self.tidsets[item as usize]);
+        }
+        tids.len()
+    }
+}
+
+/// Support counting with u128 tid-sets up to 128 transactions and BitVec ones above.
+pub(crate) enum SupportCounter {
+    Small(TidLists<Wrappedu128>),
+    Large(TidLists<WrappedBitVec>),
+}
+
+impl SupportCounter {
+    pub(crate) fn new(transactions: &[Vec<u8>]) -> Self {
+        match transactions.len() <= u128::BITS as usize {
+            true => SupportCounter::Small(TidLists::new(transactions)),
+            false => SupportCounter::Large(TidLists::new(transactions)),
+        }
+    }
+
+    pub(crate) fn support(&self, itemset: &[u8]) -> usize {
+        match self {
+            SupportCounter::Small(tid_lists) => tid_lists.support(itemset),
+            SupportCounter::Large(tid_lists) => tid_lists.support(itemset),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn transactions(nb_copies: usize) -> Vec<Vec<u8>> {
+        [vec![1, 2, 3], vec![1, 2], vec![2, 3], vec![1, 2, 3, 200]]
+            .iter()
+            .cycle()
+            .take(4 * nb_copies)
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn test_small() {
+        let counter = SupportCounter::new(&transactions(1));
+        assert!(matches!(counter, SupportCounter::Small(_)));
+        assert_eq!(counter.support(&[2]), 4);
+        assert_eq!(counter.support(&[1, 3]), 2);
+        assert_eq!(counter.support(&[3, 200]), 1);
+        assert_eq!(counter.support(&[4]), 0);
+        assert_eq!(counter.support(&[]), 4);
+    }
+
+    #[test]
+    fn test_large() {
+        let counter = SupportCounter::new(&transactions(100));
+        assert!(matches!(counter, SupportCounter::Large(_)));
+        assert_eq!(counter.support(&[1, 2]), 300);
+        assert_eq!(counter.support(&[1, 2, 3, 200]), 100);
+    }
+
+    #[test]
+    fn test_tidsets() {
+        let tid_lists = TidLists::<Wrappedu128>::new(&transactions(1));
+        let full = Wrappedu128::full(4);
+        assert_eq!(full.len(), 4);
+        assert_eq!(full.intersection_len(tid_lists.tidset(3)), 3);
+        assert_eq!(
+            tid_lists.tidset(1).intersection_len(tid_lists.tidset(200)),
+            1
+        );
+        assert_eq!(Wrappedu128::full(0).len(), 0);
+        assert_eq!(Wrappedu128::full(128).len(), 128);
+        let tid_lists = TidLists::<WrappedBitVec>::new(&transactions(100));
+        let full = WrappedBitVec::full(tid_lists.nb_transactions());
+        assert_eq!(full.intersection_len(tid_lists.tidset(3)), 300);
+    }
+}
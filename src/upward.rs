@@ -0,0 +1,198 @@
+use anyhow::{bail, Result};
+use std::str::FromStr;
+
+use crate::expander::constraint::{Constraints, ItemMask};
+use crate::expander::ExpandOptions;
+use crate::JsonSet;
+
+/// Whether the expansion generates the subsets (down) or the supersets (up) of the inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum Direction {
+    #[default]
+    Down,
+    Up,
+}
+
+impl Direction {
+    pub(crate) const NAMES: [&'static str; 2] = ["down", "up"];
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "down" => Ok(Direction::Down),
+            "up" => Ok(Direction::Up),
+            _ => Err(format!(
+                "unknown direction '{}', use one of {:?}",
+                s,
+                Self::NAMES
+            )),
+        }
+    }
+}
+
+/// Universe of items for the upward closure.
+///
+/// The supersets of an item-set S within the universe U are the complements of the subsets
+/// of U \ S, so the upward closure is the downward expansion of the complemented inputs with
+/// every output item-set complemented back. Any expander and backend can be used as is.
+pub(crate) struct Universe {
+    items: Vec<u8>,
+    mask: ItemMask,
+}
+
+impl Universe {
+    pub(crate) fn new(items: impl IntoIterator<Item = u8>) -> Self {
+        let mask = items.into_iter().collect::<ItemMask>();
+        Universe {
+            items: mask.items(),
+            mask,
+        }
+    }
+
+    /// Union of the items of the input item-sets.
+    pub(crate) fn from_input(parsed_set: &[JsonSet]) -> Self {
+        Self::new(parsed_set.iter().flat_map(|x| x.set.iter().copied()))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Items of the universe which are not in `itemset`, in increasing order.
+    pub(crate) fn complement(&self, itemset: &[u8]) -> Vec<u8> {
+        let itemset = itemset.iter().copied().collect::<ItemMask>();
+        self.items
+            .iter()
+            .copied()
+            .filter(|&x| !itemset.contains(x))
+            .collect()
+    }
+
+    /// Complements the input item-sets and maps the bounds and constraints of the upward
+    /// closure onto their downward expansion: required items become forbidden ones and vice
+    /// versa, and the size bounds are mirrored. Inputs with items outside of the universe have
+    /// no superset in it and are dropped, so are all of them if a required item is outside of
+    /// it. Forbidden items outside of the universe are never generated anyway.
+    pub(crate) fn complement_expansion(
+        &self,
+        parsed_set: Vec<JsonSet>,
+        options: &ExpandOptions,
+    ) -> Result<(Vec<JsonSet>, ExpandOptions)> {
+        if !options.constraints.at_most_one.is_empty() {
+            bail!("At-most-one groups are not supported for the upward closure");
+        }
+        if options.min_utility.is_some() {
+            bail!("A minimum utility is not supported for the upward closure");
+        }
+        let required = options.constraints.required;
+        let complemented_options = ExpandOptions {
+            min_size: self.len().saturating_sub(options.max_size),
+            max_size: self.len().saturating_sub(options.min_size),
+            constraints: Constraints {
+                required: options.constraints.forbidden.intersection(&self.mask),
+                forbidden: required.intersection(&self.mask),
+                at_most_one: Vec::new(),
+            },
+            min_utility: None,
+        };
+        if options.min_size > self.len() || !required.is_subset(&self.mask) {
+            // nothing is larger than the universe or holds items outside of it.
+            return Ok((Vec::new(), complemented_options));
+        }
+        let complemented_set = parsed_set
+            .into_iter()
+            .filter(|x| x.set.iter().all(|&item| self.mask.contains(item)))
+            .map(|x| JsonSet {
+                set: self.complement(&x.set),
+            })
+            .collect();
+        Ok((complemented_set, complemented_options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use fnv::FnvHashSet;
+
+    use crate::expander::vec::VecExpander;
+    use crate::expander::Expander;
+
+    use super::*;
+
+    fn expand_up(
+        parsed_set: Vec<JsonSet>,
+        universe: &Universe,
+        options: &ExpandOptions,
+    ) -> Vec<Vec<u8>> {
+        let (complemented_set, options) =
+            universe.complement_expansion(parsed_set, options).unwrap();
+        let expanded_set =
+            VecExpander::<FnvHashSet<Vec<u8>>>::expand_with(complemented_set, &options);
+        let mut itemsets = expanded_set
+            .iter()
+            .map(|x| universe.complement(x))
+            .collect::<Vec<_>>();
+        itemsets.sort();
+        itemsets
+    }
+
+    #[test]
+    fn test_1_universe_from_input() {
+        let parsed_set = vec![JsonSet { set: vec![1] }, JsonSet { set: vec![2, 3] }];
+        let universe = Universe::from_input(&parsed_set);
+        let itemsets = expand_up(parsed_set, &universe, &ExpandOptions::default());
+        assert_eq!(
+            itemsets,
+            vec![vec![1], vec![1, 2], vec![1, 2, 3], vec![1, 3], vec![2, 3]]
+        );
+    }
+
+    #[test]
+    fn test_2_explicit_universe() {
+        let parsed_set = vec![JsonSet { set: vec![1, 2] }, JsonSet { set: vec![7] }];
+        let universe = Universe::new([1, 2, 3, 4]);
+        let options = ExpandOptions {
+            max_size: 3,
+            constraints: Constraints {
+                forbidden: [4].into_iter().collect(),
+                ..Constraints::default()
+            },
+            ..ExpandOptions::default()
+        };
+        let itemsets = expand_up(parsed_set, &universe, &options);
+        assert_eq!(itemsets, vec![vec![1, 2], vec![1, 2, 3]]);
+    }
+
+    #[test]
+    fn test_3_constraints_outside_universe() {
+        let parsed_set = vec![JsonSet { set: vec![1] }];
+        let universe = Universe::new([1, 2]);
+        let options = ExpandOptions {
+            constraints: Constraints {
+                forbidden: [9].into_iter().collect(),
+                ..Constraints::default()
+            },
+            ..ExpandOptions::default()
+        };
+        let itemsets = expand_up(parsed_set.clone(), &universe, &options);
+        assert_eq!(itemsets, vec![vec![1], vec![1, 2]]);
+        let options = ExpandOptions {
+            constraints: Constraints {
+                required: [9].into_iter().collect(),
+                ..Constraints::default()
+            },
+            ..ExpandOptions::default()
+        };
+        assert!(expand_up(parsed_set, &universe, &options).is_empty());
+    }
+
+    #[test]
+    fn test_direction() {
+        assert_eq!("up".parse(), Ok(Direction::Up));
+        assert!("sideways".parse::<Direction>().is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::expander::hasher::{with_hash_set, HasherKind};
+use crate::expander::vec::VecExpander;
+use crate::expander::vechashonly::{Fingerprint, VecHashOnlyExpander};
+use crate::expander::{ExpandOptions, Expander};
+use crate::JsonSet;
+use anyhow::Result;
+use fnv::{FnvHashMap, FnvHashSet};
+use std::hash::Hasher;
+
+/// Exact vs hash-only expansion of the same input item-sets for one Hasher.
+pub(crate) struct CollisionReport {
+    pub(crate) hasher: HasherKind,
+    pub(crate) colliding_hashes: usize,
+    pub(crate) exact_len: usize,
+    pub(crate) hash_only_len: usize,
+}
+
+impl CollisionReport {
+    /// Item-sets lost by the hash-only expansion, either directly through a colliding hash
+    /// or through the pruning of their whole sub-lattice.
+    pub(crate) fn difference(&self) -> i64 {
+        self.exact_len as i64 - self.hash_only_len as i64
+    }
+}
+
+/// Parses a sampled fraction of the input item-sets, in (0, 1].
+pub(crate) fn parse_sample(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(sample) if sample > 0.0 && sample <= 1.0 => Ok(sample),
+        _ => Err(format!("invalid sample '{}', use a fraction in (0, 1]", s)),
+    }
+}
+
+/// Keeps every `1 / sample`th input item-set so that both expansions stay affordable.
+pub(crate) fn sample_input(parsed_set: Vec<JsonSet>, sample: f64) -> Vec<JsonSet> {
+    let step = (1.0 / sample).round().max(1.0) as usize;
+    parsed_set.into_iter().step_by(step).collect()
+}
+
+fn report<S, F>(
+    hasher: HasherKind,
+    exact_set: &FnvHashSet<Vec<u8>>,
+    parsed_set: &[JsonSet],
+    options: &ExpandOptions,
+) -> CollisionReport
+where
+    S: Hasher + Default,
+    F: Fingerprint,
+{
+    let mut hash_counts = FnvHashMap::<F, usize>::default();
+    for itemset in exact_set {
+        *hash_counts.entry(F::of::<S>(itemset)).or_default() += 1;
+    }
+    let hash_only_set =
+        VecHashOnlyExpander::<FnvHashSet<F>, S, F>::expand_with(parsed_set.to_vec(), options);
+    CollisionReport {
+        hasher,
+        colliding_hashes: hash_counts.values().filter(|&&count| count > 1).count(),
+        exact_len: exact_set.len(),
+        hash_only_len: hash_only_set.len(),
+    }
+}
+
+/// Expands `parsed_set` with `options` exactly and with the hash-only expander for every
+/// registered Hasher. The Int Table is skipped since it uses FNV hashes.
+pub(crate) fn verify_collisions<F: Fingerprint>(
+    parsed_set: &[JsonSet],
+    options: &ExpandOptions,
+) -> Result<Vec<CollisionReport>> {
+    let exact_set = VecExpander::<FnvHashSet<Vec<u8>>>::expand_with(parsed_set.to_vec(), options);
+    let mut reports = Vec::new();
+    for hasher in HasherKind::ALL
+        .into_iter()
+        .filter(|&h| h != HasherKind::Table)
+    {
+        let report = with_hash_set!(hasher, _Set, H => {
+            report::<H, F>(hasher, &exact_set, parsed_set, options)
+        });
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+pub(crate) fn print_reports(reports: &[CollisionReport], nb_sampled: usize, nb_total: usize) {
+    println!(
+        "Verified collisions on {} of {} input item-sets",
+        nb_sampled, nb_total
+    );
+    println!(
+        "{:<12}{:>18}{:>18}{:>18}{:>12}",
+        "hasher", "colliding hashes", "exact", "hash-only", "difference"
+    );
+    for r in reports {
+        println!(
+            "{:<12}{:>18}{:>18}{:>18}{:>12}",
+            r.hasher.name(),
+            r.colliding_hashes,
+            r.exact_len,
+            r.hash_only_len,
+            r.difference()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_sample_input() {
+        let parsed_set = (0..10).map(|i| JsonSet { set: vec![i] }).collect();
+        let sampled = sample_input(parsed_set, 0.25);
+        assert_eq!(
+            sampled.iter().map(|x| x.set[0]).collect::<Vec<_>>(),
+            vec![0, 4, 8]
+        );
+    }
+
+    #[test]
+    fn test_parse_sample() {
+        assert_eq!(parse_sample("0.25"), Ok(0.25));
+        assert_eq!(parse_sample("1"), Ok(1.0));
+        for s in ["0", "-0.5", "1.5", "NaN", "x"] {
+            assert!(parse_sample(s).is_err());
+        }
+    }
+
+    #[test]
+    fn test_no_collisions_wide() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![57, 58, 59, 60],
+            },
+            JsonSet { set: vec![60, 99] },
+        ];
+        for r in verify_collisions::<u128>(&parsed_set, &ExpandOptions::default()).unwrap() {
+            assert_eq!(r.colliding_hashes, 0);
+            assert_eq!(r.exact_len, 17);
+            assert_eq!(r.difference(), 0);
+        }
+    }
+
+    #[test]
+    fn test_options() {
+        let parsed_set = vec![
+            JsonSet {
+                set: vec![57, 58, 59, 60],
+            },
+            JsonSet { set: vec![60, 99] },
+        ];
+        let options = ExpandOptions {
+            min_size: 2,
+            max_size: 3,
+            ..ExpandOptions::default()
+        };
+        for r in verify_collisions::<u128>(&parsed_set, &options).unwrap() {
+            assert_eq!(r.exact_len, 11);
+            assert_eq!(r.hash_only_len, 11);
+        }
+    }
+}